@@ -0,0 +1,49 @@
+const { invoke } = window.__TAURI__.tauri;
+
+let changePassphraseFormEl;
+let oldPassphraseInputEl;
+let newPassphraseInputEl;
+let newPassphraseConfirmInputEl;
+let changeButtonEl;
+let errorEl;
+
+window.addEventListener("DOMContentLoaded", async () => {
+  changePassphraseFormEl = document.querySelector("#change-passphrase-form");
+  oldPassphraseInputEl = document.querySelector("#old-passphrase-input");
+  newPassphraseInputEl = document.querySelector("#new-passphrase-input");
+  newPassphraseConfirmInputEl = document.querySelector("#new-passphrase-confirm-input");
+  changeButtonEl = document.querySelector("#change-button");
+  errorEl = document.querySelector("#error");
+
+  // the current passphrase is known if it is still the one of earlier versions
+  if (window.__KANGAROO_LEGACY_PASSPHRASE__) {
+    oldPassphraseInputEl.value = window.__KANGAROO_LEGACY_PASSPHRASE__;
+    oldPassphraseInputEl.style.display = "none";
+    document.querySelector("#legacy-notice").style.display = "block";
+  }
+
+  newPassphraseConfirmInputEl.addEventListener("input", () => {
+    if (newPassphraseConfirmInputEl.value !== newPassphraseInputEl.value) {
+      newPassphraseConfirmInputEl.setCustomValidity("The passphrases don't match.");
+    } else {
+      newPassphraseConfirmInputEl.setCustomValidity("");
+    }
+  });
+
+  changePassphraseFormEl.addEventListener("submit", async (e) => {
+    e.preventDefault();
+    errorEl.innerHTML = "";
+    changeButtonEl.disabled = true;
+    changeButtonEl.innerHTML = "Changing...";
+    try {
+      await invoke("change_passphrase", {
+        oldPassphrase: oldPassphraseInputEl.value,
+        newPassphrase: newPassphraseInputEl.value,
+      });
+    } catch (e) {
+      errorEl.innerHTML = e;
+      changeButtonEl.disabled = false;
+      changeButtonEl.innerHTML = "Change and Restart";
+    }
+  });
+});
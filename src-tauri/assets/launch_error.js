@@ -0,0 +1,47 @@
+const { invoke } = window.__TAURI__.tauri;
+
+let errorEl;
+
+async function invokeAction(command) {
+  errorEl.innerHTML = "";
+  try {
+    await invoke(command, {});
+  } catch (e) {
+    errorEl.innerHTML = e;
+  }
+}
+
+window.addEventListener("DOMContentLoaded", async () => {
+  const launchError = window.__KANGAROO_LAUNCH_ERROR__;
+
+  errorEl = document.querySelector("#error");
+  document.querySelector("#explanation").innerText = launchError.explanation;
+  document.querySelector("#details").innerText = `${launchError.kind}\n\n${launchError.details}`;
+
+  document
+    .querySelector("#retry-button")
+    .addEventListener("click", () => invokeAction("retry_launch"));
+  document
+    .querySelector("#open-logs-button")
+    .addEventListener("click", () => invokeAction("open_logs"));
+  document
+    .querySelector("#switch-profile-button")
+    .addEventListener("click", () => invokeAction("open_profile_settings"));
+  document
+    .querySelector("#manage-storage-button")
+    .addEventListener("click", () => invokeAction("open_storage_manager"));
+
+  // resetting deletes data, so it needs a second click to confirm
+  const resetButtonEl = document.querySelector("#reset-conductor-button");
+  let resetConfirmed = false;
+  resetButtonEl.addEventListener("click", async () => {
+    if (!resetConfirmed) {
+      resetConfirmed = true;
+      resetButtonEl.innerHTML = "Click again to delete all app data (keys are kept)";
+      return;
+    }
+    resetButtonEl.disabled = true;
+    await invokeAction("reset_profile_conductor");
+    resetButtonEl.disabled = false;
+  });
+});
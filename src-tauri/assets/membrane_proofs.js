@@ -0,0 +1,48 @@
+const { invoke } = window.__TAURI__.tauri;
+const { listen } = window.__TAURI__.event;
+
+let submitButtonEl;
+let errorEl;
+
+function setDisabled(disabled) {
+  submitButtonEl.disabled = disabled;
+  document.querySelectorAll("textarea").forEach((el) => (el.disabled = disabled));
+}
+
+window.addEventListener("DOMContentLoaded", async () => {
+  const roles = window.__KANGAROO_MEMBRANE_PROOF_ROLES__;
+
+  submitButtonEl = document.querySelector("#submit-button");
+  errorEl = document.querySelector("#error");
+
+  const rolesEl = document.querySelector("#roles");
+  roles.forEach((role) => {
+    const labelEl = document.createElement("label");
+    labelEl.innerText = role;
+    const inputEl = document.createElement("textarea");
+    inputEl.dataset.role = role;
+    inputEl.required = true;
+    rolesEl.appendChild(labelEl);
+    rolesEl.appendChild(inputEl);
+  });
+
+  await listen("membrane-proof-error", (event) => {
+    errorEl.innerText = event.payload;
+    submitButtonEl.innerHTML = "Install";
+    setDisabled(false);
+  });
+
+  document.querySelector("#membrane-proofs-form").addEventListener("submit", async (e) => {
+    e.preventDefault();
+    errorEl.innerText = "";
+    const proofs = {};
+    document.querySelectorAll("textarea").forEach((el) => (proofs[el.dataset.role] = el.value));
+    try {
+      await invoke("submit_membrane_proofs", { proofs });
+      setDisabled(true);
+      submitButtonEl.innerHTML = "Installing...";
+    } catch (e) {
+      errorEl.innerText = e;
+    }
+  });
+});
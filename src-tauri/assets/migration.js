@@ -0,0 +1,57 @@
+const { invoke } = window.__TAURI__.tauri;
+
+let profilesEl;
+let errorEl;
+
+function renderProfiles(profiles) {
+  profilesEl.innerHTML = "";
+  profiles.forEach((migration) => {
+    const itemEl = document.createElement("li");
+    itemEl.innerText = migration.profile;
+    if (migration.problem) {
+      const problemEl = document.createElement("div");
+      problemEl.classList.add("problem");
+      problemEl.innerText = `Can't be carried forward: ${migration.problem}`;
+      itemEl.appendChild(problemEl);
+    }
+    profilesEl.appendChild(itemEl);
+  });
+}
+
+window.addEventListener("DOMContentLoaded", () => {
+  const candidate = window.__KANGAROO_MIGRATION_CANDIDATE__;
+
+  profilesEl = document.querySelector("#profiles");
+  errorEl = document.querySelector("#error");
+
+  document.querySelector("#description").innerText =
+    `A previous version (${candidate.from_version}) of this app left the following profiles behind. Do you want to carry them forward to this version?`;
+  renderProfiles(candidate.profiles);
+
+  document.querySelector("#migrate-button").addEventListener("click", async () => {
+    errorEl.innerText = "";
+    try {
+      const results = await invoke("migrate_profiles", { fromVersion: candidate.from_version });
+      renderProfiles(results);
+      document.querySelector("#description").innerText = results.some((r) => r.problem)
+        ? "Some profiles could not be carried forward:"
+        : "All profiles were carried forward.";
+      document.querySelector("#choice").classList.add("hidden");
+      document.querySelector("#note").classList.add("hidden");
+      document.querySelector("#done").classList.remove("hidden");
+    } catch (e) {
+      errorEl.innerText = e;
+    }
+  });
+
+  document.querySelector("#decline-button").addEventListener("click", async () => {
+    try {
+      await invoke("decline_migration", {});
+      await invoke("restart", {});
+    } catch (e) {
+      errorEl.innerText = e;
+    }
+  });
+
+  document.querySelector("#continue-button").addEventListener("click", () => invoke("restart", {}));
+});
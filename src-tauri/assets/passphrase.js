@@ -0,0 +1,60 @@
+const { invoke } = window.__TAURI__.tauri;
+const { listen } = window.__TAURI__.event;
+
+let passphraseFormEl;
+let passphraseInputEl;
+let passphraseConfirmInputEl;
+let submitButtonEl;
+let errorEl;
+
+window.addEventListener("DOMContentLoaded", async () => {
+  const mode = window.__KANGAROO_PASSPHRASE_MODE__;
+
+  passphraseFormEl = document.querySelector("#passphrase-form");
+  passphraseInputEl = document.querySelector("#passphrase-input");
+  passphraseConfirmInputEl = document.querySelector("#passphrase-confirm-input");
+  submitButtonEl = document.querySelector("#submit-button");
+  errorEl = document.querySelector("#error");
+
+  if (mode === "setup") {
+    document.querySelector("#title").innerHTML = "Set up your passphrase";
+    document.querySelector("#description").innerHTML =
+      "Choose a passphrase to protect your keys and data. It will be required every time you open the app and cannot be recovered if you forget it.";
+    passphraseConfirmInputEl.classList.remove("hidden");
+    passphraseConfirmInputEl.required = true;
+    submitButtonEl.innerHTML = "Set Passphrase";
+  } else {
+    document.querySelector("#description").innerHTML = "Enter your passphrase to unlock the app.";
+  }
+
+  passphraseConfirmInputEl.addEventListener("input", () => {
+    if (passphraseConfirmInputEl.value !== passphraseInputEl.value) {
+      passphraseConfirmInputEl.setCustomValidity("The passphrases don't match.");
+    } else {
+      passphraseConfirmInputEl.setCustomValidity("");
+    }
+  });
+
+  await listen("passphrase-error", (event) => {
+    errorEl.innerHTML = event.payload;
+    submitButtonEl.innerHTML = mode === "setup" ? "Set Passphrase" : "Unlock";
+    submitButtonEl.disabled = false;
+    passphraseInputEl.disabled = false;
+    passphraseConfirmInputEl.disabled = false;
+    passphraseInputEl.select();
+  });
+
+  passphraseFormEl.addEventListener("submit", async (e) => {
+    e.preventDefault();
+    errorEl.innerHTML = "";
+    try {
+      await invoke("submit_passphrase", { passphrase: passphraseInputEl.value });
+      submitButtonEl.disabled = true;
+      passphraseInputEl.disabled = true;
+      passphraseConfirmInputEl.disabled = true;
+      submitButtonEl.innerHTML = mode === "setup" ? "Setting up..." : "Unlocking...";
+    } catch (e) {
+      errorEl.innerHTML = e;
+    }
+  });
+});
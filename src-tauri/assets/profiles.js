@@ -17,12 +17,17 @@ window.addEventListener("DOMContentLoaded", async () => {
   currentProfileSpanEl.style["padding"] = "3px 5px";
 
   const allProfiles = await invoke("get_existing_profiles", {});
+  const migratedFrom = await invoke("get_profiles_migrated_from", {});
   selectProfileEl = document.querySelector("#profile-selector");
   console.log("all profiles: ", allProfiles);
   allProfiles.forEach((profile) => {
     const option = document.createElement("option");
     option.setAttribute("name", profile);
+    option.value = profile;
     option.innerHTML = profile;
+    if (migratedFrom[profile]) {
+      option.innerHTML = `${profile} (migrated from ${migratedFrom[profile]})`;
+    }
     selectProfileEl.appendChild(option);
   });
 
@@ -0,0 +1,32 @@
+const { invoke } = window.__TAURI__.tauri;
+const { listen } = window.__TAURI__.event;
+
+function renderProgress(event) {
+  const stageEl = document.querySelector(`#stage-${event.stage}`);
+  if (!stageEl) return;
+  const statusEl = stageEl.querySelector(".status");
+
+  stageEl.classList.remove("started", "done", "skipped");
+  stageEl.classList.add(event.status);
+
+  switch (event.status) {
+    case "started":
+      statusEl.innerHTML = "...";
+      break;
+    case "done":
+      statusEl.innerHTML =
+        event.duration_ms !== null ? `${(event.duration_ms / 1000).toFixed(1)}s` : "done";
+      break;
+    case "skipped":
+      statusEl.innerHTML = "skipped";
+      break;
+  }
+}
+
+window.addEventListener("DOMContentLoaded", async () => {
+  // listen first so that no stage gets lost between fetching the history and listening
+  await listen("launch-progress", (event) => renderProgress(event.payload));
+
+  const events = await invoke("get_launch_progress");
+  events.forEach(renderProgress);
+});
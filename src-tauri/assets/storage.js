@@ -0,0 +1,113 @@
+const { invoke } = window.__TAURI__.tauri;
+
+let versionsEl;
+let archivesEl;
+let errorEl;
+
+function formatSize(bytes) {
+  const units = ["B", "KB", "MB", "GB", "TB"];
+  let size = bytes;
+  let unit = 0;
+  while (size >= 1024 && unit < units.length - 1) {
+    size /= 1024;
+    unit++;
+  }
+  return `${size.toFixed(unit === 0 ? 0 : 1)} ${units[unit]}`;
+}
+
+// removing data needs a second click to confirm
+function actionButton(label, confirmLabel, command, args) {
+  const buttonEl = document.createElement("button");
+  buttonEl.innerText = label;
+  buttonEl.style.marginLeft = "5px";
+  let confirmed = false;
+  buttonEl.addEventListener("click", async () => {
+    if (!confirmed) {
+      confirmed = true;
+      buttonEl.innerText = confirmLabel;
+      return;
+    }
+    errorEl.innerText = "";
+    document.querySelectorAll("button").forEach((el) => (el.disabled = true));
+    try {
+      await invoke(command, args);
+    } catch (e) {
+      errorEl.innerText = e;
+    }
+    await refresh();
+  });
+  return buttonEl;
+}
+
+function row(className, name, sizeBytes, inUse, buttons) {
+  const rowEl = document.createElement("tr");
+  rowEl.className = className;
+
+  const nameEl = document.createElement("td");
+  nameEl.innerText = name;
+  if (inUse) {
+    const inUseEl = document.createElement("span");
+    inUseEl.className = "in-use";
+    inUseEl.innerText = " in use";
+    nameEl.appendChild(inUseEl);
+  }
+  const sizeEl = document.createElement("td");
+  sizeEl.className = "size";
+  sizeEl.innerText = formatSize(sizeBytes);
+  const actionsEl = document.createElement("td");
+  actionsEl.className = "actions";
+  if (!inUse) {
+    buttons.forEach((buttonEl) => actionsEl.appendChild(buttonEl));
+  }
+
+  rowEl.append(nameEl, sizeEl, actionsEl);
+  return rowEl;
+}
+
+async function refresh() {
+  let usage;
+  try {
+    usage = await invoke("get_storage_usage", {});
+  } catch (e) {
+    errorEl.innerText = e;
+    return;
+  }
+
+  versionsEl.innerHTML = "";
+  usage.versions.forEach((version) => {
+    versionsEl.appendChild(
+      row("version", version.version, version.size_bytes, version.in_use, [
+        actionButton("Archive", "Confirm archive", "archive_storage", { version: version.version }),
+        actionButton("Delete", "Confirm delete", "delete_storage", { version: version.version }),
+      ])
+    );
+    version.profiles.forEach((profile) => {
+      const args = { version: version.version, profile: profile.profile };
+      versionsEl.appendChild(
+        row("profile", profile.profile, profile.size_bytes, profile.in_use, [
+          actionButton("Archive", "Confirm archive", "archive_storage", args),
+          actionButton("Delete", "Confirm delete", "delete_storage", args),
+        ])
+      );
+    });
+  });
+
+  archivesEl.innerHTML = "";
+  if (usage.archives.length === 0) {
+    archivesEl.innerHTML = "<tr><td>No archives</td></tr>";
+  }
+  usage.archives.forEach((archive) => {
+    archivesEl.appendChild(
+      row("archive", archive.file_name, archive.size_bytes, false, [
+        actionButton("Delete", "Confirm delete", "delete_archive", { fileName: archive.file_name }),
+      ])
+    );
+  });
+}
+
+window.addEventListener("DOMContentLoaded", async () => {
+  versionsEl = document.querySelector("#versions");
+  archivesEl = document.querySelector("#archives");
+  errorEl = document.querySelector("#error");
+  await refresh();
+});
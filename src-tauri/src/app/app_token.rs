@@ -0,0 +1,104 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use holochain_client::{AppWebsocket, ClientAgentSigner, IssueAppAuthenticationTokenPayload};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    app_state::AppState,
+    errors::{AppError, AppResult},
+    process::shutdown::is_shutting_down,
+    utils::get_admin_ws,
+};
+
+/// Seconds an app authentication token stays valid. Tokens are single use, so they only need to
+/// live until the UI has connected to the app interface.
+const APP_TOKEN_EXPIRY_SECONDS: u64 = 60;
+
+/// Issues a single use token to connect to the app interface and records it for the given
+/// window, so that it can be revoked once the window is closed
+pub async fn issue_app_token(state: &AppState, window_label: &str) -> AppResult<Vec<u8>> {
+    let token = issue_single_use_token(state).await?;
+
+    state
+        .app_tokens
+        .lock()
+        .unwrap()
+        .entry(window_label.to_string())
+        .or_default()
+        .push(token.clone());
+
+    Ok(token)
+}
+
+/// Connects to the app interface on behalf of the app itself, e.g. to manage clone cells.
+/// The token is used right away, so it isn't recorded for revocation.
+pub async fn connect_app_ws(state: &AppState) -> AppResult<AppWebsocket> {
+    let token = issue_single_use_token(state).await?;
+    AppWebsocket::connect(
+        SocketAddr::from(([127, 0, 0, 1], state.app_port)),
+        token,
+        Arc::new(ClientAgentSigner::default()),
+    )
+    .await
+    .map_err(|e| {
+        AppError::AppWebsocketError(format!("Could not connect to the app interface: {}", e))
+    })
+}
+
+async fn issue_single_use_token(state: &AppState) -> AppResult<Vec<u8>> {
+    let admin_ws = get_admin_ws(state.admin_port).await?;
+    let token = admin_ws
+        .issue_app_auth_token(IssueAppAuthenticationTokenPayload {
+            installed_app_id: state.config.app_id.clone(),
+            expiry_seconds: APP_TOKEN_EXPIRY_SECONDS,
+            single_use: true,
+        })
+        .await
+        .map_err(AppError::ConductorApiError)?
+        .token;
+    Ok(token)
+}
+
+/// Revokes all tokens issued for the given window that may not have been used yet
+pub fn revoke_app_tokens(app_handle: &AppHandle, window_label: &str) {
+    let state = match app_handle.try_state::<AppState>() {
+        Some(state) => state,
+        None => return,
+    };
+    let tokens = state
+        .app_tokens
+        .lock()
+        .unwrap()
+        .remove(window_label)
+        .unwrap_or_default();
+    // the conductor is being stopped anyway
+    if tokens.is_empty() || is_shutting_down() {
+        return;
+    }
+
+    let admin_port = state.admin_port;
+    let window_label = window_label.to_string();
+    tauri::async_runtime::spawn(async move {
+        let admin_ws = match get_admin_ws(admin_port).await {
+            Ok(admin_ws) => admin_ws,
+            Err(e) => {
+                log::warn!(
+                    "Failed to revoke the app tokens of window {}: {}",
+                    window_label,
+                    e
+                );
+                return;
+            }
+        };
+        for token in tokens {
+            // tokens that were used or have expired are gone already
+            if let Err(e) = admin_ws.revoke_app_authentication_token(token).await {
+                log::debug!(
+                    "Could not revoke an app token of window {}: {:?}",
+                    window_label,
+                    e
+                );
+            }
+        }
+    });
+}
@@ -0,0 +1,157 @@
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Window, WindowBuilder};
+
+use crate::errors::{
+    AppError, InitializeConductorError, LairKeystoreError, LaunchChildError, LaunchHolochainError,
+};
+
+pub const LAUNCH_ERROR_WINDOW_LABEL: &str = "launch_error";
+
+/// What the launch error window shows about an error
+#[derive(Serialize, Debug, Clone)]
+pub struct LaunchErrorReport {
+    /// Name of the error variant, e.g. `LaunchHolochainError::CouldNotInitializeConductor`
+    pub kind: String,
+    /// Explanation of the error for the user
+    pub explanation: String,
+    /// The error message itself
+    pub details: String,
+}
+
+impl From<&AppError> for LaunchErrorReport {
+    fn from(error: &AppError) -> Self {
+        let (kind, explanation) = explain_error(error);
+        LaunchErrorReport {
+            kind: String::from(kind),
+            explanation: String::from(explanation),
+            details: error.to_string(),
+        }
+    }
+}
+
+fn explain_error(error: &AppError) -> (&'static str, &'static str) {
+    match error {
+        AppError::LaunchHolochainError(LaunchHolochainError::CouldNotInitializeConductor(e)) => {
+            match e {
+                InitializeConductorError::SqliteError(_) => (
+                    "InitializeConductorError::SqliteError",
+                    "The conductor could not open its databases. They may be corrupted or encrypted with a different passphrase. Resetting the conductor data usually fixes this.",
+                ),
+                InitializeConductorError::AddressAlreadyInUse(_) => (
+                    "InitializeConductorError::AddressAlreadyInUse",
+                    "A port the conductor needs is already taken by another program. Retrying usually fixes this.",
+                ),
+                InitializeConductorError::DatabaseLocked(_) => (
+                    "InitializeConductorError::DatabaseLocked",
+                    "The databases of the conductor are used by another process. Another instance of this app may still be running for this profile.",
+                ),
+                InitializeConductorError::KeystoreError(_) => (
+                    "InitializeConductorError::KeystoreError",
+                    "The conductor could not connect to the keystore holding your keys. Retrying usually fixes this.",
+                ),
+                InitializeConductorError::InvalidConfig(_) => (
+                    "InitializeConductorError::InvalidConfig",
+                    "The conductor rejected its configuration.",
+                ),
+                InitializeConductorError::PermissionDenied(_) => (
+                    "InitializeConductorError::PermissionDenied",
+                    "The conductor is not allowed to access its data directory or a port it needs.",
+                ),
+                InitializeConductorError::DiskFull(_) => (
+                    "InitializeConductorError::DiskFull",
+                    "There is not enough disk space left for the conductor. Free up some space and retry.",
+                ),
+                InitializeConductorError::ProcessExitedEarly(_) => (
+                    "InitializeConductorError::ProcessExitedEarly",
+                    "The conductor stopped before it was ready. The logs may contain more details.",
+                ),
+                InitializeConductorError::UnknownError(_) => (
+                    "InitializeConductorError::UnknownError",
+                    "The conductor failed to start for an unknown reason. The logs may contain more details.",
+                ),
+            }
+        }
+        AppError::LaunchHolochainError(LaunchHolochainError::LaunchChildError(
+            LaunchChildError::BinaryNotFound,
+        )) => (
+            "LaunchChildError::BinaryNotFound",
+            "The holochain binary is missing from this installation. Reinstalling the app should fix this.",
+        ),
+        AppError::LaunchHolochainError(LaunchHolochainError::CouldNotConnectToConductor(_)) => (
+            "LaunchHolochainError::CouldNotConnectToConductor",
+            "The conductor was started but did not respond. Retrying usually fixes this.",
+        ),
+        AppError::LaunchHolochainError(_) => (
+            "LaunchHolochainError",
+            "The conductor could not be started.",
+        ),
+        AppError::LairKeystoreError(LairKeystoreError::IncorrectPassword) => (
+            "LairKeystoreError::IncorrectPassword",
+            "The passphrase could not unlock the keystore.",
+        ),
+        AppError::LairKeystoreError(LairKeystoreError::StartupTimeout(_)) => (
+            "LairKeystoreError::StartupTimeout",
+            "The keystore holding your keys took too long to start. Retrying usually fixes this.",
+        ),
+        AppError::LairKeystoreError(LairKeystoreError::ProcessExitedEarly(_)) => (
+            "LairKeystoreError::ProcessExitedEarly",
+            "The keystore holding your keys stopped before it was ready. The logs may contain more details.",
+        ),
+        AppError::LairKeystoreError(LairKeystoreError::PassphraseUnavailable(_)) => (
+            "LairKeystoreError::PassphraseUnavailable",
+            "No passphrase was provided to unlock the keystore. Check the passphrase settings in kangaroo.toml.",
+        ),
+        AppError::LairKeystoreError(_) => (
+            "LairKeystoreError",
+            "The keystore holding your keys could not be started.",
+        ),
+        AppError::ConfigError(_) => (
+            "AppError::ConfigError",
+            "A kangaroo.toml configuration file or KANGAROO_ environment variable is invalid.",
+        ),
+        AppError::ConductorApiError(_)
+        | AppError::AdminWebsocketError(_)
+        | AppError::AppWebsocketError(_) => (
+            "AppError::ConductorApiError",
+            "The conductor is running but rejected a request while setting up the app.",
+        ),
+        AppError::MembraneProofError(_) => (
+            "AppError::MembraneProofError",
+            "The membrane proofs needed to join the network of this app are missing or were rejected. Check the membrane proofs passed on the command line or in the membrane-proofs.json of the profile.",
+        ),
+        AppError::FileSystemError(_) | AppError::IoError(_) => (
+            "AppError::FileSystemError",
+            "Files in the app data directory could not be read or written.",
+        ),
+        _ => ("AppError", "An unexpected error occurred while starting up."),
+    }
+}
+
+/// Shows the error that made the launch fail, together with actions to recover from it
+pub fn build_launch_error_window(
+    app_handle: &AppHandle,
+    error: &AppError,
+) -> tauri::Result<Window> {
+    let report = LaunchErrorReport::from(error);
+    let report_json = serde_json::to_string(&report)?;
+
+    if let Some(window) = app_handle.get_window(LAUNCH_ERROR_WINDOW_LABEL) {
+        window.show()?;
+        window.set_focus()?;
+        return Ok(window);
+    }
+
+    WindowBuilder::new(
+        app_handle,
+        LAUNCH_ERROR_WINDOW_LABEL,
+        tauri::WindowUrl::App(
+            std::path::PathBuf::from("kangaroo_assets").join("launch_error.html"),
+        ),
+    )
+    .title("Failed to start")
+    .inner_size(580.0, 480.0)
+    .center()
+    .resizable(true)
+    .initialization_script(format!("window.__KANGAROO_LAUNCH_ERROR__ = {};", report_json).as_str())
+    .build()
+}
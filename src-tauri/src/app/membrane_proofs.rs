@@ -0,0 +1,64 @@
+use futures::channel::mpsc::UnboundedSender;
+use tauri::{AppHandle, Manager, Window, WindowBuilder};
+
+use crate::membrane_proofs::MembraneProofs;
+
+pub const MEMBRANE_PROOFS_WINDOW_LABEL: &str = "membrane_proofs";
+
+/// Sending half of the channel through which the membrane proofs window hands the
+/// membrane proofs entered by the user over to the launch task
+pub struct MembraneProofsSender(pub UnboundedSender<MembraneProofs>);
+
+pub fn build_membrane_proofs_window(
+    app_handle: &AppHandle,
+    roles: &[String],
+) -> tauri::Result<Window> {
+    if let Some(window) = app_handle.get_window(MEMBRANE_PROOFS_WINDOW_LABEL) {
+        window.show()?;
+        window.set_focus()?;
+        return Ok(window);
+    }
+
+    WindowBuilder::new(
+        app_handle,
+        MEMBRANE_PROOFS_WINDOW_LABEL,
+        tauri::WindowUrl::App(
+            std::path::PathBuf::from("kangaroo_assets").join("membrane_proofs.html"),
+        ),
+    )
+    .title("Membrane Proofs")
+    .inner_size(600.0, 480.0)
+    .center()
+    .minimizable(false)
+    .initialization_script(
+        format!(
+            "window.__KANGAROO_MEMBRANE_PROOF_ROLES__ = {};",
+            serde_json::to_string(roles)?
+        )
+        .as_str(),
+    )
+    .build()
+}
+
+/// Shows why the membrane proofs were rejected and lets the user enter different ones
+pub fn report_membrane_proof_error(app_handle: &AppHandle, message: String) {
+    match app_handle.get_window(MEMBRANE_PROOFS_WINDOW_LABEL) {
+        Some(window) => {
+            if let Err(e) = window.emit("membrane-proof-error", message) {
+                log::error!("Failed to report membrane proof error to the window: {}", e);
+            }
+        }
+        None => log::error!(
+            "Membrane proofs window not found to report error: {}",
+            message
+        ),
+    }
+}
+
+pub fn close_membrane_proofs_window(app_handle: &AppHandle) {
+    if let Some(window) = app_handle.get_window(MEMBRANE_PROOFS_WINDOW_LABEL) {
+        if let Err(e) = window.close() {
+            log::error!("Failed to close the membrane proofs window: {}", e);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use tauri::{AppHandle, Manager, Window, WindowBuilder};
+
+use crate::migration::MigrationCandidate;
+
+pub const MIGRATION_WINDOW_LABEL: &str = "migration";
+
+/// Offers to carry the profiles of an older version of the app forward
+pub fn build_migration_window(
+    app_handle: &AppHandle,
+    candidate: &MigrationCandidate,
+) -> tauri::Result<Window> {
+    if let Some(window) = app_handle.get_window(MIGRATION_WINDOW_LABEL) {
+        window.show()?;
+        window.set_focus()?;
+        return Ok(window);
+    }
+
+    WindowBuilder::new(
+        app_handle,
+        MIGRATION_WINDOW_LABEL,
+        tauri::WindowUrl::App(std::path::PathBuf::from("kangaroo_assets").join("migration.html")),
+    )
+    .title("Data of a Previous Version")
+    .inner_size(600.0, 480.0)
+    .center()
+    .minimizable(false)
+    .initialization_script(
+        format!(
+            "window.__KANGAROO_MIGRATION_CANDIDATE__ = {};",
+            serde_json::to_string(candidate)?
+        )
+        .as_str(),
+    )
+    .build()
+}
@@ -3,15 +3,33 @@ use crate::{
         filesystem::{AppFileSystem, Profile},
         AppState,
     },
-    config,
+    commands::passphrase::build_change_passphrase_window,
+    config::KangarooConfig,
+    errors::AppResult,
     launch::launch,
     logs::setup_logs,
+    membrane_proofs::{membrane_proof_provider, MembraneProofProvider},
+    migration::find_migration_candidate,
+    origins::allowed_origins,
+    passphrase::{PassphraseProvider, PassphraseProviderConfig},
+    process::supervisor::supervise_conductor,
 };
 use futures::lock::Mutex;
+use launch_error::{build_launch_error_window, LAUNCH_ERROR_WINDOW_LABEL};
+use membrane_proofs::close_membrane_proofs_window;
+use migration::build_migration_window;
+use passphrase::{close_passphrase_window, PASSPHRASE_WINDOW_LABEL};
 use serde_json::Value;
-use tauri::{App, Manager};
-use window::build_main_window;
+use splash::{build_splash_window, close_splash_window, LaunchProgress};
+use tauri::{App, AppHandle, Manager};
+use window::{ask_to_reset_unrestricted_interface, build_main_window};
 
+pub mod app_token;
+pub mod launch_error;
+pub mod membrane_proofs;
+pub mod migration;
+pub mod passphrase;
+pub mod splash;
 pub mod system_tray;
 pub mod window;
 
@@ -31,17 +49,19 @@ pub fn setup_app(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
                         window.show().unwrap();
                         window.unminimize().unwrap();
                         window.set_focus().unwrap();
-                    } else {
-                        let state = app.state::<AppState>().inner().to_owned();
+                    } else if let Some(state) = app.try_state::<AppState>() {
                         tauri::async_runtime::block_on(async {
-                            build_main_window(
-                                state.fs.clone(),
-                                app,
-                                state.app_port,
-                                state.admin_port,
-                            )
-                            .await;
+                            if let Err(e) = build_main_window(app, &state).await {
+                                log::error!("Failed to open the main window: {}", e);
+                            }
                         })
+                    } else if let Some(window) = app
+                        .get_window(PASSPHRASE_WINDOW_LABEL)
+                        .or_else(|| app.get_window(LAUNCH_ERROR_WINDOW_LABEL))
+                    {
+                        // still waiting for the passphrase, or the launch failed
+                        window.show().unwrap();
+                        window.set_focus().unwrap();
                     }
                 },
             ))?;
@@ -51,27 +71,150 @@ pub fn setup_app(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
-    tauri::async_runtime::block_on(async move {
-        let fs = AppFileSystem::new(&handle, &profile).unwrap();
-        // set up logs
-        if let Err(err) = setup_logs(fs.clone()) {
-            println!("Error setting up the logs: {:?}", err);
+    let fs = AppFileSystem::new(&handle, &profile)?;
+
+    // the config is resolved first since the log file is named after the app id. If it can't be
+    // loaded, the logs go to the file of the default app id.
+    let kangaroo_config = KangarooConfig::load(&fs);
+    let app_id = match &kangaroo_config {
+        Ok(kangaroo_config) => kangaroo_config.app_id.clone(),
+        Err(_) => KangarooConfig::default().app_id,
+    };
+    // set up logs
+    if let Err(err) = setup_logs(fs.clone(), &app_id) {
+        println!("Error setting up the logs: {:?}", err);
+    }
+    app.manage(fs.clone());
+
+    // the data of an older breaking version is only found and carried forward on request
+    match find_migration_candidate(&fs) {
+        Ok(Some(candidate)) => {
+            log::info!(
+                "Found profiles of version {} to migrate",
+                candidate.from_version
+            );
+            build_migration_window(&handle, &candidate)?;
+            return Ok(());
+        }
+        Ok(None) => {}
+        Err(e) => log::error!("Failed to look for data of older versions: {}", e),
+    }
+
+    let kangaroo_config = match kangaroo_config {
+        Ok(kangaroo_config) => {
+            kangaroo_config.log_resolved(&fs);
+            kangaroo_config
+        }
+        Err(e) => {
+            log::error!("Failed to load the config: {:?}", e);
+            build_launch_error_window(&handle, &e)?;
+            return Ok(());
+        }
+    };
+
+    let passphrase_provider = kangaroo_config.passphrase.build(&handle);
+    let membrane_proof_provider = match membrane_proof_provider(app, &fs) {
+        Ok(membrane_proof_provider) => membrane_proof_provider,
+        Err(e) => {
+            log::error!("Failed to read the membrane proof arguments: {:?}", e);
+            build_launch_error_window(&handle, &e)?;
+            return Ok(());
         }
-        let (meta_lair_client, app_port, admin_port) =
-            launch(&fs, config::PASSWORD.to_string()).await.unwrap();
-        let app_state = AppState {
-            fs: fs.clone(),
-            app_port,
-            admin_port,
-            meta_lair_client: Mutex::new(meta_lair_client),
-        };
-        app.manage(app_state);
-        build_main_window(fs, &app.app_handle(), app_port, admin_port).await;
+    };
+
+    app.manage(LaunchProgress::new(handle.clone()));
+    build_splash_window(&handle)?;
+
+    tauri::async_runtime::spawn(async move {
+        launch_app(
+            handle,
+            fs,
+            kangaroo_config,
+            passphrase_provider,
+            membrane_proof_provider,
+        )
+        .await
     });
 
     Ok(())
 }
 
+/// Launches holochain, asking the passphrase provider for the passphrase, and opens the main window
+async fn launch_app(
+    app_handle: AppHandle,
+    fs: AppFileSystem,
+    kangaroo_config: KangarooConfig,
+    passphrase_provider: Box<dyn PassphraseProvider>,
+    membrane_proof_provider: Box<dyn MembraneProofProvider>,
+) {
+    if let Err(e) = try_launch_app(
+        &app_handle,
+        fs,
+        kangaroo_config,
+        passphrase_provider.as_ref(),
+        membrane_proof_provider.as_ref(),
+    )
+    .await
+    {
+        log::error!("Failed to launch: {:?}", e);
+        close_passphrase_window(&app_handle);
+        close_membrane_proofs_window(&app_handle);
+        close_splash_window(&app_handle);
+        if let Err(e) = build_launch_error_window(&app_handle, &e) {
+            log::error!("Failed to open the launch error window: {}", e);
+        }
+    }
+}
+
+async fn try_launch_app(
+    app_handle: &AppHandle,
+    fs: AppFileSystem,
+    kangaroo_config: KangarooConfig,
+    passphrase_provider: &dyn PassphraseProvider,
+    membrane_proof_provider: &dyn MembraneProofProvider,
+) -> AppResult<()> {
+    let progress = app_handle.state::<LaunchProgress>();
+    let prompts_passphrase = kangaroo_config.passphrase == PassphraseProviderConfig::Prompt;
+    let launched = launch(
+        &fs,
+        &kangaroo_config,
+        passphrase_provider,
+        membrane_proof_provider,
+        allowed_origins(app_handle, &kangaroo_config),
+        progress.inner(),
+    )
+    .await?;
+
+    let app_state = AppState {
+        fs,
+        config: kangaroo_config,
+        app_port: launched.app_port,
+        admin_port: launched.admin_port,
+        lair_url: Mutex::new(launched.lair_url),
+        meta_lair_client: Mutex::new(launched.meta_lair_client),
+        app_tokens: Default::default(),
+        #[cfg(feature = "embedded-conductor")]
+        conductor_handle: launched.conductor_handle,
+    };
+    app_handle.manage(app_state);
+    if let Some(conductor_process) = launched.conductor_process {
+        supervise_conductor(app_handle.clone(), conductor_process);
+    }
+    let main_window = build_main_window(app_handle, &app_handle.state::<AppState>()).await?;
+    close_passphrase_window(app_handle);
+    close_membrane_proofs_window(app_handle);
+    close_splash_window(app_handle);
+
+    if launched.legacy_passphrase && prompts_passphrase {
+        build_change_passphrase_window(app_handle, true)?;
+    }
+    if launched.unrestricted_app_interface.is_some() {
+        ask_to_reset_unrestricted_interface(&main_window);
+    }
+
+    Ok(())
+}
+
 pub fn read_profile_from_cli(app: &mut App) -> Result<Option<Profile>, tauri::Error> {
     // reading profile from cli
     let cli_matches = app.get_cli_matches()?;
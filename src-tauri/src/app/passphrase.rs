@@ -0,0 +1,66 @@
+use futures::channel::mpsc::UnboundedSender;
+use tauri::{AppHandle, Manager, Window, WindowBuilder};
+
+use crate::passphrase::PassphraseMode;
+
+pub const PASSPHRASE_WINDOW_LABEL: &str = "passphrase";
+
+/// Sending half of the channel through which the passphrase window hands the
+/// passphrase entered by the user over to the launch task
+pub struct PassphraseSender(pub UnboundedSender<String>);
+
+pub fn build_passphrase_window(
+    app_handle: &AppHandle,
+    mode: PassphraseMode,
+) -> tauri::Result<Window> {
+    if let Some(window) = app_handle.get_window(PASSPHRASE_WINDOW_LABEL) {
+        window.show()?;
+        window.set_focus()?;
+        return Ok(window);
+    }
+
+    WindowBuilder::new(
+        app_handle,
+        PASSPHRASE_WINDOW_LABEL,
+        tauri::WindowUrl::App(std::path::PathBuf::from("kangaroo_assets").join("passphrase.html")),
+    )
+    .title(match mode {
+        PassphraseMode::Setup => "Set up Passphrase",
+        PassphraseMode::Unlock => "Unlock",
+    })
+    .inner_size(500.0, 380.0)
+    .center()
+    .resizable(false)
+    .minimizable(false)
+    .initialization_script(
+        format!(
+            r#"window.__KANGAROO_PASSPHRASE_MODE__ = "{}";"#,
+            match mode {
+                PassphraseMode::Setup => "setup",
+                PassphraseMode::Unlock => "unlock",
+            }
+        )
+        .as_str(),
+    )
+    .build()
+}
+
+/// Shows an error in the passphrase window and lets the user enter the passphrase again
+pub fn report_passphrase_error(app_handle: &AppHandle, message: String) {
+    match app_handle.get_window(PASSPHRASE_WINDOW_LABEL) {
+        Some(window) => {
+            if let Err(e) = window.emit("passphrase-error", message) {
+                log::error!("Failed to report passphrase error to the window: {}", e);
+            }
+        }
+        None => log::error!("Passphrase window not found to report error: {}", message),
+    }
+}
+
+pub fn close_passphrase_window(app_handle: &AppHandle) {
+    if let Some(window) = app_handle.get_window(PASSPHRASE_WINDOW_LABEL) {
+        if let Err(e) = window.close() {
+            log::error!("Failed to close the passphrase window: {}", e);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::{collections::HashMap, sync::Mutex, time::Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Window, WindowBuilder};
+
+pub const SPLASH_WINDOW_LABEL: &str = "splash";
+
+/// Name of the Tauri event carrying a `LaunchProgressEvent`
+pub const LAUNCH_PROGRESS_EVENT: &str = "launch-progress";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum LaunchStage {
+    KeystoreInit,
+    KeystoreUnlock,
+    ConductorStart,
+    InterfaceAttach,
+    AppInstall,
+    AppEnable,
+    CoordinatorUpdate,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LaunchStageStatus {
+    Started,
+    Done,
+    Skipped,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LaunchProgressEvent {
+    pub stage: LaunchStage,
+    pub status: LaunchStageStatus,
+    /// Time the stage took, set once it is done
+    pub duration_ms: Option<u128>,
+}
+
+/// Tracks the stages of the launch, logs their timing and reports them to the splash window.
+/// Managed as Tauri state so that the splash window can catch up on the stages it missed
+/// while loading.
+pub struct LaunchProgress {
+    app_handle: AppHandle,
+    started_at: Mutex<HashMap<LaunchStage, Instant>>,
+    events: Mutex<Vec<LaunchProgressEvent>>,
+}
+
+impl LaunchProgress {
+    pub fn new(app_handle: AppHandle) -> Self {
+        LaunchProgress {
+            app_handle,
+            started_at: Mutex::new(HashMap::new()),
+            events: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn start(&self, stage: LaunchStage) {
+        log::info!("[LAUNCH] {:?} started", stage);
+        self.started_at
+            .lock()
+            .unwrap()
+            .insert(stage, Instant::now());
+        self.report(LaunchProgressEvent {
+            stage,
+            status: LaunchStageStatus::Started,
+            duration_ms: None,
+        });
+    }
+
+    pub fn finish(&self, stage: LaunchStage) {
+        let duration = self
+            .started_at
+            .lock()
+            .unwrap()
+            .get(&stage)
+            .map(|started_at| started_at.elapsed());
+        log::info!("[LAUNCH] {:?} done after {:?}", stage, duration);
+        self.report(LaunchProgressEvent {
+            stage,
+            status: LaunchStageStatus::Done,
+            duration_ms: duration.map(|d| d.as_millis()),
+        });
+    }
+
+    pub fn skip(&self, stage: LaunchStage) {
+        log::info!("[LAUNCH] {:?} skipped", stage);
+        self.report(LaunchProgressEvent {
+            stage,
+            status: LaunchStageStatus::Skipped,
+            duration_ms: None,
+        });
+    }
+
+    pub fn events(&self) -> Vec<LaunchProgressEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    fn report(&self, event: LaunchProgressEvent) {
+        self.events.lock().unwrap().push(event.clone());
+        if let Err(e) = self
+            .app_handle
+            .emit_to(SPLASH_WINDOW_LABEL, LAUNCH_PROGRESS_EVENT, event)
+        {
+            log::error!("Failed to report launch progress: {}", e);
+        }
+    }
+}
+
+pub fn build_splash_window(app_handle: &AppHandle) -> tauri::Result<Window> {
+    WindowBuilder::new(
+        app_handle,
+        SPLASH_WINDOW_LABEL,
+        tauri::WindowUrl::App(std::path::PathBuf::from("kangaroo_assets").join("splash.html")),
+    )
+    .title(crate::config::WINDOW_TITLE)
+    .inner_size(420.0, 320.0)
+    .center()
+    .resizable(false)
+    .decorations(false)
+    .build()
+}
+
+pub fn close_splash_window(app_handle: &AppHandle) {
+    if let Some(window) = app_handle.get_window(SPLASH_WINDOW_LABEL) {
+        if let Err(e) = window.close() {
+            log::error!("Failed to close the splash window: {}", e);
+        }
+    }
+}
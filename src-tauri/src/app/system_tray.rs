@@ -1,8 +1,13 @@
-use super::window::build_main_window;
-use crate::app_state::AppState;
+use super::{
+    launch_error::LAUNCH_ERROR_WINDOW_LABEL, passphrase::PASSPHRASE_WINDOW_LABEL,
+    window::build_main_window,
+};
+use crate::{
+    app_state::AppState,
+    process::shutdown::{shutdown_and_exit, shutdown_and_restart},
+};
 use tauri::{
-    api::process, AppHandle, CustomMenuItem, Manager, SystemTrayEvent, SystemTrayMenu,
-    SystemTrayMenuItem, Wry,
+    AppHandle, CustomMenuItem, Manager, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem, Wry,
 };
 
 pub fn app_system_tray() -> SystemTrayMenu {
@@ -29,22 +34,23 @@ fn handle_system_tray_menu_item_click(app: &AppHandle<Wry>, event_id: &str) {
                 window.show().unwrap();
                 window.unminimize().unwrap();
                 window.set_focus().unwrap();
-            } else {
-                let state = app.state::<AppState>().inner().to_owned();
+            } else if let Some(state) = app.try_state::<AppState>() {
                 tauri::async_runtime::block_on(async {
-                    build_main_window(state.fs.clone(), app, state.app_port, state.admin_port)
-                        .await;
+                    if let Err(e) = build_main_window(app, &state).await {
+                        log::error!("Failed to open the main window: {}", e);
+                    }
                 });
+            } else if let Some(window) = app
+                .get_window(PASSPHRASE_WINDOW_LABEL)
+                .or_else(|| app.get_window(LAUNCH_ERROR_WINDOW_LABEL))
+            {
+                // still waiting for the passphrase, or the launch failed
+                window.show().unwrap();
+                window.set_focus().unwrap();
             }
         }
-        "restart" => {
-            process::kill_children();
-            app.app_handle().restart();
-        }
-        "quit" => {
-            process::kill_children();
-            app.exit(0);
-        }
+        "restart" => shutdown_and_restart(app.app_handle()),
+        "quit" => shutdown_and_exit(app.app_handle()),
         _ => (),
     }
 }
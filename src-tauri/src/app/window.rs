@@ -1,75 +1,102 @@
-use std::net::SocketAddr;
+use tauri::api::dialog::{ask, message};
+use tauri::{
+    AppHandle, CustomMenuItem, Manager, Menu, Submenu, Window, WindowBuilder, WindowEvent, Wry,
+};
 
-use holochain_client::{AdminWebsocket, IssueAppAuthenticationTokenPayload};
-use tauri::api::dialog::message;
-use tauri::api::process;
-use tauri::{AppHandle, CustomMenuItem, Manager, Menu, Submenu, Window, WindowBuilder, Wry};
-
-use crate::app_state::AppState;
-use crate::commands::profile::open_profile_settings;
+use crate::app_state::{filesystem::AppFileSystem, AppState};
+use crate::commands::{
+    passphrase::open_change_passphrase,
+    profile::{
+        open_profile_settings, reset_profile_conductor_and_restart,
+        reset_profile_identity_and_restart,
+    },
+    storage::open_storage_manager,
+};
 use crate::config;
-use crate::{app_state::filesystem::AppFileSystem, logs::open_logs_folder};
+use crate::errors::AppResult;
+use crate::logs::open_logs_folder;
+use crate::process::shutdown::{shutdown_and_exit, shutdown_and_restart};
+
+use super::app_token::{issue_app_token, revoke_app_tokens};
 
-pub async fn build_main_window(
-    fs: AppFileSystem,
-    app_handle: &AppHandle,
-    app_port: u16,
-    admin_port: u16,
-) -> Window {
-    let ws = match AdminWebsocket::connect(SocketAddr::from(([127, 0, 0, 1], admin_port))).await {
-        Ok(ws) => ws,
-        Err(e) => panic!("Failed to connect to admin websocket: {:?}", e),
-    };
-    let result = ws
-        .issue_app_auth_token(IssueAppAuthenticationTokenPayload {
-            installed_app_id: config::APP_ID.to_string(),
-            expiry_seconds: 999999,
-            single_use: false,
-        })
-        .await;
-    let app_authentication_token = match result {
-        Ok(r) => r.token,
-        Err(e) => panic!("Failed to issue app authentication token: {:?}", e),
-    };
+const MAIN_WINDOW_LABEL: &str = "main";
 
-    WindowBuilder::new(
+pub async fn build_main_window(app_handle: &AppHandle, state: &AppState) -> AppResult<Window> {
+    let kangaroo_config = &state.config;
+    let app_authentication_token = issue_app_token(state, MAIN_WINDOW_LABEL).await?;
+
+    let window = WindowBuilder::new(
         &app_handle.app_handle(),
-        "main",
+        MAIN_WINDOW_LABEL,
         tauri::WindowUrl::App("index.html".into()),
     )
     // optional (OSmenu) -- Adds an OS menu to the app
     .menu(build_menu())
     // optional -- diables file drop handler. Disabling is required for drag and drop to work on certain platforms
     .disable_file_drop_handler()
-    .inner_size(config::WINDOW_WIDTH, config::WINDOW_HEIGHT)
+    .inner_size(kangaroo_config.window_width, kangaroo_config.window_height)
     .resizable(true)
     .title(config::WINDOW_TITLE)
-    .data_directory(fs.profile_data_dir)
+    .data_directory(state.fs.profile_data_dir.clone())
     .center()
     .initialization_script(
         format!(
             r#"window.__HC_LAUNCHER_ENV__ = {{
               "APP_INTERFACE_PORT": {}, 
-              "ADMIN_INTERFACE_PORT": {}, 
+              {}
               "INSTALLED_APP_ID": "{}", 
               "APP_INTERFACE_TOKEN": {:?},
+            }};
+            // the token above is single use and only valid for the first load of the page, every
+            // later load, e.g. a reload or a navigation, fetches a fresh one
+            if (sessionStorage.getItem("__HC_APP_INTERFACE_TOKEN_USED__")) {{
+              window.__HC_LAUNCHER_ENV__.APP_INTERFACE_TOKEN = undefined;
+              window.__HC_APP_INTERFACE_TOKEN_READY__ = window.__TAURI__.tauri
+                .invoke("refresh_app_token")
+                .then((token) => {{
+                  window.__HC_LAUNCHER_ENV__.APP_INTERFACE_TOKEN = token;
+                  return token;
+                }});
+            }} else {{
+              sessionStorage.setItem("__HC_APP_INTERFACE_TOKEN_USED__", "true");
+              window.__HC_APP_INTERFACE_TOKEN_READY__ = Promise.resolve(
+                window.__HC_LAUNCHER_ENV__.APP_INTERFACE_TOKEN
+              );
             }}"#,
-            app_port,
-            admin_port,
-            config::APP_ID,
+            state.app_port,
+            // the admin port gives full control over the conductor, so it is withheld unless configured otherwise
+            match kangaroo_config.expose_admin_port {
+                true => format!(r#""ADMIN_INTERFACE_PORT": {},"#, state.admin_port),
+                false => String::new(),
+            },
+            kangaroo_config.app_id,
             app_authentication_token,
         )
         .as_str(),
     )
     .initialization_script(include_str!("../initialization_scripts/zoomOnScroll.js"))
     .initialization_script(include_str!("../initialization_scripts/signZomeCall.js"))
-    .build()
-    .unwrap()
+    .initialization_script(include_str!("../initialization_scripts/conductorStatus.js"))
+    .build()?;
+
+    // tokens that were issued for this window but not used must not outlive it
+    let handle = app_handle.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            revoke_app_tokens(&handle, MAIN_WINDOW_LABEL);
+        }
+    });
+
+    Ok(window)
 }
 
 pub fn build_menu() -> Menu {
     let version = CustomMenuItem::new("version", "Version");
     let change_profile = CustomMenuItem::new("change_profile", "Change Profile");
+    let change_passphrase = CustomMenuItem::new("change_passphrase", "Change Passphrase");
+    let manage_storage = CustomMenuItem::new("manage_storage", "Manage Storage");
+    let reset_conductor = CustomMenuItem::new("reset_conductor", "Reset Conductor Data");
+    let new_identity = CustomMenuItem::new("new_identity", "New Identity");
     let open_logs = CustomMenuItem::new("open_logs", "Open Logs");
     let devtools = CustomMenuItem::new("devtools", "Open DevTools");
     let restart = CustomMenuItem::new("restart", "Restart");
@@ -80,6 +107,10 @@ pub fn build_menu() -> Menu {
         Menu::new()
             .add_item(version.clone())
             .add_item(change_profile.clone())
+            .add_item(change_passphrase.clone())
+            .add_item(manage_storage.clone())
+            .add_item(reset_conductor.clone())
+            .add_item(new_identity.clone())
             .add_item(open_logs.clone())
             .add_item(devtools.clone())
             .add_item(restart.clone())
@@ -93,6 +124,10 @@ pub fn build_menu() -> Menu {
             Menu::new()
                 .add_item(version)
                 .add_item(change_profile)
+                .add_item(change_passphrase)
+                .add_item(manage_storage)
+                .add_item(reset_conductor)
+                .add_item(new_identity)
                 .add_item(open_logs)
                 .add_item(devtools)
                 .add_item(restart)
@@ -105,9 +140,29 @@ pub fn build_menu() -> Menu {
     Menu::new().add_submenu(menu_submenu)
 }
 
+/// Offers to reset the conductor data to get rid of an app interface that an earlier version
+/// attached, which lets any web page on this computer connect to the conductor
+pub fn ask_to_reset_unrestricted_interface(window: &Window<Wry>) {
+    let app_handle = window.app_handle();
+    ask(
+        Some(window),
+        "Reset Conductor Data",
+        "An earlier version of this app opened the conductor to any web page on this computer, and the conductor can't close it again. Resetting the conductor data removes it. Your keys are kept and the app will be installed again under the same identity, but data that has not been synced to other peers is lost. Reset now?",
+        move |confirmed| {
+            if confirmed {
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = reset_profile_conductor_and_restart(app_handle).await {
+                        log::error!("{}", e);
+                    }
+                });
+            }
+        },
+    );
+}
+
 pub fn handle_menu_event(event_id: &str, window: &Window<Wry>) {
     let app_handle = window.app_handle();
-    let state = app_handle.state::<AppState>();
+    let fs = app_handle.state::<AppFileSystem>();
     match event_id {
         "version" => message(
             Some(&window),
@@ -118,16 +173,40 @@ pub fn handle_menu_event(event_id: &str, window: &Window<Wry>) {
             ),
         ),
         "change_profile" => open_profile_settings(app_handle).unwrap(),
-        "open_logs" => open_logs_folder(state.fs.to_owned()),
+        "change_passphrase" => open_change_passphrase(app_handle).unwrap(),
+        "manage_storage" => open_storage_manager(app_handle).unwrap(),
+        "reset_conductor" => ask(
+            Some(&window),
+            "Reset Conductor Data",
+            "This deletes the conductor data of the active profile and restarts the app. Your keys are kept and the app will be installed again under the same identity, but data that has not been synced to other peers is lost. Continue?",
+            move |confirmed| {
+                if confirmed {
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = reset_profile_conductor_and_restart(app_handle).await {
+                            log::error!("{}", e);
+                        }
+                    });
+                }
+            },
+        ),
+        "new_identity" => ask(
+            Some(&window),
+            "New Identity",
+            "This deletes the conductor data of the active profile and restarts the app, which is then installed again under a newly generated agent key. Your data will no longer be associated with you. Continue?",
+            move |confirmed| {
+                if confirmed {
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = reset_profile_identity_and_restart(app_handle).await {
+                            log::error!("{}", e);
+                        }
+                    });
+                }
+            },
+        ),
+        "open_logs" => open_logs_folder(fs.inner().clone()),
         "devtools" => window.open_devtools(),
-        "restart" => {
-            process::kill_children();
-            app_handle.restart();
-        }
-        "quit" => {
-            process::kill_children();
-            app_handle.exit(0)
-        }
+        "restart" => shutdown_and_restart(app_handle),
+        "quit" => shutdown_and_exit(app_handle),
         _ => {}
     }
 }
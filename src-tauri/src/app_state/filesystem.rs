@@ -7,13 +7,15 @@ use crate::{
     utils::breaking_app_version,
 };
 
+use super::metadata::{read_profile_metadata, update_profile_metadata, ProfileMetadata};
+
 pub type Profile = String;
 
 #[derive(Debug, Clone)]
 pub struct AppFileSystem {
     pub app_data_dir: PathBuf,
+    pub app_config_dir: PathBuf,
     pub profile_data_dir: PathBuf,
-    #[allow(dead_code)]
     pub profile_config_dir: PathBuf,
     pub profile_log_dir: PathBuf,
 }
@@ -30,12 +32,15 @@ impl AppFileSystem {
 
         let profile_data_dir = app_data_dir.join(profile);
 
-        let profile_config_dir = app_handle
-            .path_resolver()
-            .app_config_dir()
-            .ok_or(AppError::FileSystemError(String::from(
-                "Could not resolve the data dir for this app",
-            )))?
+        let app_config_dir =
+            app_handle
+                .path_resolver()
+                .app_config_dir()
+                .ok_or(AppError::FileSystemError(String::from(
+                    "Could not resolve the config dir for this app",
+                )))?;
+
+        let profile_config_dir = app_config_dir
             .join(breaking_app_version(app_handle)?)
             .join(profile);
 
@@ -50,6 +55,7 @@ impl AppFileSystem {
 
         Ok(AppFileSystem {
             app_data_dir,
+            app_config_dir,
             profile_data_dir,
             profile_config_dir,
             profile_log_dir,
@@ -70,6 +76,17 @@ impl AppFileSystem {
         self.profile_data_dir.join("conductor")
     }
 
+    /// Deletes the conductor databases of this profile. The keystore is kept, so that
+    /// the agent keys survive and the app can be installed again with the same identity.
+    pub fn reset_conductor_data(&self) -> AppResult<()> {
+        let conductor_dir = self.conductor_dir();
+        if conductor_dir.exists() {
+            log::warn!("Deleting conductor data at {:?}", conductor_dir);
+            std::fs::remove_dir_all(&conductor_dir)?;
+        }
+        Ok(())
+    }
+
     pub fn get_existing_profiles(&self) -> Result<Vec<Profile>, String> {
         let mut profiles = Vec::new();
         let dir_entries = std::fs::read_dir(&self.app_data_dir)
@@ -129,6 +146,19 @@ impl AppFileSystem {
         Ok(())
     }
 
+    /// Reads the metadata of this profile. A missing or unreadable file is treated as empty metadata.
+    pub fn read_profile_metadata(&self) -> ProfileMetadata {
+        read_profile_metadata(&self.profile_data_dir)
+    }
+
+    /// Applies the given change to the metadata of this profile and writes it back to disk
+    pub fn update_profile_metadata(
+        &self,
+        update: impl FnOnce(&mut ProfileMetadata),
+    ) -> AppResult<()> {
+        update_profile_metadata(&self.profile_data_dir, update)
+    }
+
     pub fn read_profile_network_seed(&self) -> Option<String> {
         let network_seed_path = self.profile_data_dir.join(".networkSeed");
         if !network_seed_path.exists() {
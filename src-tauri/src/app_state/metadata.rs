@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{AppError, AppResult};
+
+/// Name of the file in the profile data directory holding the `ProfileMetadata`
+pub const PROFILE_METADATA_FILE_NAME: &str = ".profileMetadata.json";
+
+/// What the app keeps track of about a profile across launches
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ProfileMetadata {
+    /// Coordinator updates applied to the installed app, oldest first
+    pub coordinator_updates: Vec<CoordinatorUpdateRecord>,
+    /// Data directory of an older version of the app this profile was migrated from
+    pub migrated_from: Option<String>,
+    /// Whether the keystore is known to be locked by a passphrase of the user's choice rather
+    /// than the `LEGACY_PASSPHRASE` of earlier versions, which is then no longer tried first
+    pub passphrase_chosen: bool,
+    /// Agent key the app was installed under, to install it under the same one again after
+    /// the conductor data has been reset
+    pub agent_pub_key: Option<String>,
+    /// Set when the user asked for a new identity, so that the next install generates a new
+    /// agent key instead of looking for the previous one in the keystore
+    pub new_identity_requested: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CoordinatorUpdateRecord {
+    /// RFC 3339 timestamp of the update
+    pub timestamp: String,
+    pub role_name: String,
+    pub dna_hash: String,
+    /// Names of the coordinator zomes of the bundled happ that were installed
+    pub zomes: Vec<String>,
+    /// Error of the conductor if the update failed
+    pub error: Option<String>,
+}
+
+/// Reads the metadata of the profile in the given data directory. A missing or unreadable file
+/// is treated as empty metadata.
+pub fn read_profile_metadata(profile_data_dir: &Path) -> ProfileMetadata {
+    let metadata_path = profile_data_dir.join(PROFILE_METADATA_FILE_NAME);
+    if !metadata_path.exists() {
+        return ProfileMetadata::default();
+    }
+    match std::fs::read_to_string(metadata_path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            log::error!("Failed to read profile metadata from file: {}", e);
+            ProfileMetadata::default()
+        }
+    }
+}
+
+/// Applies the given change to the metadata of the profile in the given data directory and
+/// writes it back to disk
+pub fn update_profile_metadata(
+    profile_data_dir: &Path,
+    update: impl FnOnce(&mut ProfileMetadata),
+) -> AppResult<()> {
+    let mut metadata = read_profile_metadata(profile_data_dir);
+    update(&mut metadata);
+    let json = serde_json::to_string_pretty(&metadata).map_err(|e| {
+        AppError::FileSystemError(format!("Failed to serialize profile metadata: {}", e))
+    })?;
+    std::fs::create_dir_all(profile_data_dir)?;
+    std::fs::write(profile_data_dir.join(PROFILE_METADATA_FILE_NAME), json)?;
+    Ok(())
+}
@@ -1,12 +1,26 @@
+use std::collections::HashMap;
+
 use filesystem::AppFileSystem;
 use futures::lock::Mutex;
+#[cfg(feature = "embedded-conductor")]
+use holochain::conductor::ConductorHandle;
 use holochain_keystore::MetaLairClient;
+use url2::Url2;
+
+use crate::config::KangarooConfig;
 
 pub mod filesystem;
+pub mod metadata;
 
 pub struct AppState {
     pub fs: AppFileSystem,
+    pub config: KangarooConfig,
     pub app_port: u16,
     pub admin_port: u16,
+    pub lair_url: Mutex<Url2>,
     pub meta_lair_client: Mutex<MetaLairClient>,
+    /// App authentication tokens issued per window label, revoked when the window is closed
+    pub app_tokens: std::sync::Mutex<HashMap<String, Vec<Vec<u8>>>>,
+    #[cfg(feature = "embedded-conductor")]
+    pub conductor_handle: Option<ConductorHandle>,
 }
@@ -0,0 +1,34 @@
+use holochain::conductor::api::AppInfo;
+
+use crate::{
+    app_state::AppState,
+    config::AdminCommand,
+    errors::{AppError, AppResult},
+    utils::get_admin_ws,
+};
+
+// Curated admin operations for the UI, which doesn't get the admin port itself.
+// Each of them needs to be enabled in the `admin_commands` of the config.
+
+pub fn ensure_enabled(state: &AppState, command: AdminCommand) -> AppResult<()> {
+    if !state.config.admin_commands.contains(&command) {
+        return Err(AppError::AdminCommandNotEnabled(command));
+    }
+    Ok(())
+}
+
+/// Returns the info of the app of this window
+#[tauri::command]
+pub async fn app_info(state: tauri::State<'_, AppState>) -> AppResult<Option<AppInfo>> {
+    ensure_enabled(&state, AdminCommand::AppInfo)?;
+
+    let admin_ws = get_admin_ws(state.admin_port).await?;
+    let apps = admin_ws
+        .list_apps(None)
+        .await
+        .map_err(|e| AppError::ConductorApiError(e))?;
+
+    Ok(apps
+        .into_iter()
+        .find(|app| app.installed_app_id == state.config.app_id))
+}
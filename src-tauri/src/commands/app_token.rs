@@ -0,0 +1,13 @@
+use crate::{app::app_token::issue_app_token, app_state::AppState};
+
+/// Issues a fresh single use token for the app interface, for every load of the UI after the
+/// first one and for the UI to reconnect with after the token it was loaded with has been used
+#[tauri::command]
+pub async fn refresh_app_token(
+    window: tauri::Window,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<u8>, String> {
+    issue_app_token(&state, window.label())
+        .await
+        .map_err(|e| format!("Failed to issue an app token: {}", e))
+}
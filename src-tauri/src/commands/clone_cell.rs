@@ -0,0 +1,119 @@
+use holochain::conductor::api::{
+    CreateCloneCellPayload, DisableCloneCellPayload, EnableCloneCellPayload,
+};
+use holochain_types::prelude::{
+    CloneCellId, ClonedCell, DeleteCloneCellPayload, DnaModifiersOpt, RoleName, YamlProperties,
+};
+use serde::Deserialize;
+
+use crate::{
+    app::app_token::connect_app_ws,
+    app_state::AppState,
+    commands::admin::ensure_enabled,
+    config::AdminCommand,
+    errors::{AppError, AppResult},
+    membrane_proofs::{decode_membrane_proof, to_membrane_proof},
+    utils::get_admin_ws,
+};
+
+// Clone cell operations on the app of this window. Creating, enabling and disabling go through
+// the app interface, deleting needs the admin interface and has to be enabled in the
+// `admin_commands` of the config.
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreateCloneCellRequest {
+    pub role_name: RoleName,
+    pub network_seed: Option<String>,
+    pub properties: Option<serde_json::Value>,
+    pub name: Option<String>,
+    /// Base64 encoded membrane proof, if the DNA requires one
+    pub membrane_proof: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CloneCellRequest {
+    pub clone_cell_id: CloneCellId,
+}
+
+#[tauri::command]
+pub async fn create_clone_cell(
+    state: tauri::State<'_, AppState>,
+    request: CreateCloneCellRequest,
+) -> AppResult<ClonedCell> {
+    let mut modifiers = DnaModifiersOpt::none();
+    if let Some(network_seed) = request.network_seed {
+        modifiers = modifiers.with_network_seed(network_seed);
+    }
+    if let Some(properties) = request.properties {
+        let properties = serde_yaml::to_value(properties).map_err(|e| {
+            AppError::CloneCellError(format!("Invalid properties for the clone cell: {}", e))
+        })?;
+        modifiers = modifiers.with_properties(YamlProperties::new(properties));
+    }
+    let membrane_proof = request
+        .membrane_proof
+        .map(|proof| decode_membrane_proof(&request.role_name, &proof).map(to_membrane_proof))
+        .transpose()?;
+
+    let app_ws = connect_app_ws(&state).await?;
+    app_ws
+        .create_clone_cell(CreateCloneCellPayload {
+            role_name: request.role_name.clone(),
+            modifiers,
+            membrane_proof,
+            name: request.name,
+        })
+        .await
+        .map_err(|e| {
+            AppError::CloneCellError(format!(
+                "Failed to create a clone cell for role {}: {:?}",
+                request.role_name, e
+            ))
+        })
+}
+
+#[tauri::command]
+pub async fn enable_clone_cell(
+    state: tauri::State<'_, AppState>,
+    request: CloneCellRequest,
+) -> AppResult<ClonedCell> {
+    let app_ws = connect_app_ws(&state).await?;
+    app_ws
+        .enable_clone_cell(EnableCloneCellPayload {
+            clone_cell_id: request.clone_cell_id,
+        })
+        .await
+        .map_err(|e| AppError::CloneCellError(format!("Failed to enable clone cell: {:?}", e)))
+}
+
+#[tauri::command]
+pub async fn disable_clone_cell(
+    state: tauri::State<'_, AppState>,
+    request: CloneCellRequest,
+) -> AppResult<()> {
+    let app_ws = connect_app_ws(&state).await?;
+    app_ws
+        .disable_clone_cell(DisableCloneCellPayload {
+            clone_cell_id: request.clone_cell_id,
+        })
+        .await
+        .map_err(|e| AppError::CloneCellError(format!("Failed to disable clone cell: {:?}", e)))
+}
+
+/// Deletes a disabled clone cell for good
+#[tauri::command]
+pub async fn delete_clone_cell(
+    state: tauri::State<'_, AppState>,
+    request: CloneCellRequest,
+) -> AppResult<()> {
+    ensure_enabled(&state, AdminCommand::DeleteCloneCell)?;
+
+    let admin_ws = get_admin_ws(state.admin_port).await?;
+    admin_ws
+        .delete_clone_cell(DeleteCloneCellPayload {
+            app_id: state.config.app_id.clone(),
+            clone_cell_id: request.clone_cell_id,
+        })
+        .await
+        .map_err(|e| AppError::CloneCellError(format!("Failed to delete clone cell: {:?}", e)))
+}
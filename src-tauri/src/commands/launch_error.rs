@@ -0,0 +1,18 @@
+use crate::{
+    app_state::filesystem::AppFileSystem, logs::open_logs_folder,
+    process::shutdown::shutdown_and_restart,
+};
+
+/// Restarts the app to launch holochain again
+#[tauri::command]
+pub fn retry_launch(app_handle: tauri::AppHandle) -> Result<(), String> {
+    log::warn!("Retrying the launch. Restarting...");
+    shutdown_and_restart(app_handle);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn open_logs(fs: tauri::State<'_, AppFileSystem>) -> Result<(), String> {
+    open_logs_folder(fs.inner().clone());
+    Ok(())
+}
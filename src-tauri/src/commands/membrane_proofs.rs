@@ -0,0 +1,25 @@
+use std::collections::HashMap;
+
+use crate::{
+    app::membrane_proofs::MembraneProofsSender,
+    errors::AppResult,
+    membrane_proofs::{decode_membrane_proof, MembraneProofs},
+};
+
+/// Hands the base64 encoded membrane proofs entered in the membrane proofs window over to the
+/// launch task
+#[tauri::command]
+pub fn submit_membrane_proofs(
+    sender: tauri::State<'_, MembraneProofsSender>,
+    proofs: HashMap<String, String>,
+) -> Result<(), String> {
+    let proofs = proofs
+        .iter()
+        .map(|(role, proof)| Ok((role.clone(), decode_membrane_proof(role, proof)?)))
+        .collect::<AppResult<MembraneProofs>>()
+        .map_err(|e| e.to_string())?;
+    sender
+        .0
+        .unbounded_send(proofs)
+        .map_err(|_| String::from("The app is not waiting for membrane proofs anymore."))
+}
@@ -0,0 +1,20 @@
+use crate::{
+    app_state::filesystem::AppFileSystem,
+    migration::{self, ProfileMigration},
+};
+
+/// Migrates the profiles of the given older version, reporting the ones that couldn't be
+/// migrated. The app needs to be restarted afterwards.
+#[tauri::command]
+pub fn migrate_profiles(
+    fs: tauri::State<'_, AppFileSystem>,
+    from_version: String,
+) -> Result<Vec<ProfileMigration>, String> {
+    migration::migrate_profiles(&fs, &from_version).map_err(|e| e.to_string())
+}
+
+/// Starts this version without the data of older versions and stops offering it
+#[tauri::command]
+pub fn decline_migration(fs: tauri::State<'_, AppFileSystem>) -> Result<(), String> {
+    migration::decline_migration(&fs).map_err(|e| e.to_string())
+}
@@ -1,4 +1,13 @@
+pub mod admin;
+pub mod app_token;
+pub mod clone_cell;
+pub mod launch_error;
 pub mod log;
+pub mod membrane_proofs;
+pub mod migration;
+pub mod passphrase;
 pub mod profile;
+pub mod progress;
 pub mod restart;
 pub mod sign_zome_call;
+pub mod storage;
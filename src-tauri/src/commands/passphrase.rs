@@ -0,0 +1,128 @@
+use tauri::{
+    api::dialog::{MessageDialogBuilder, MessageDialogKind},
+    Manager, WindowBuilder,
+};
+
+use crate::{
+    app::passphrase::PassphraseSender,
+    app_state::AppState,
+    passphrase::{PassphraseProviderConfig, LEGACY_PASSPHRASE},
+    process::shutdown::shutdown,
+    rekey,
+};
+
+/// Hands the passphrase entered in the passphrase window over to the launch task
+#[tauri::command]
+pub fn submit_passphrase(
+    sender: tauri::State<'_, PassphraseSender>,
+    passphrase: String,
+) -> Result<(), String> {
+    if passphrase.is_empty() {
+        return Err(String::from("The passphrase must not be empty."));
+    }
+    sender
+        .0
+        .unbounded_send(passphrase)
+        .map_err(|_| String::from("The app is not waiting for a passphrase anymore."))
+}
+
+/// Changes the passphrase of the keystore and the conductor databases of the active profile and
+/// restarts the app afterwards. The old passphrase is verified against the running keystore first.
+#[tauri::command]
+pub async fn change_passphrase(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    old_passphrase: String,
+    new_passphrase: String,
+) -> Result<(), String> {
+    if state.config.passphrase != PassphraseProviderConfig::Prompt {
+        return Err(format!(
+            "The passphrase of this profile is provided by {:?} and can't be changed from the app.",
+            state.config.passphrase
+        ));
+    }
+    if new_passphrase.is_empty() {
+        return Err(String::from("The new passphrase must not be empty."));
+    }
+
+    holochain_keystore::lair_keystore::spawn_lair_keystore(
+        state.lair_url.lock().await.clone(),
+        old_passphrase.as_bytes().into(),
+    )
+    .await
+    .map_err(|e| {
+        log::warn!("Failed to verify the old passphrase: {}", e);
+        String::from("Incorrect passphrase.")
+    })?;
+
+    log::warn!("Changing the passphrase. Stopping holochain and lair-keystore...");
+    tauri::async_runtime::spawn_blocking(shutdown)
+        .await
+        .map_err(|e| format!("Failed to stop holochain: {}", e))?;
+
+    match rekey::change_passphrase(&state.fs, old_passphrase, new_passphrase).await {
+        Ok(()) => {
+            if let Err(e) = state
+                .fs
+                .update_profile_metadata(|metadata| metadata.passphrase_chosen = true)
+            {
+                log::error!("Failed to record that the passphrase was changed: {}", e);
+            }
+            log::info!("Passphrase changed. Restarting...");
+            app_handle.restart();
+        }
+        Err(e) => {
+            log::error!("Failed to change the passphrase: {}", e);
+            MessageDialogBuilder::new(
+                "Failed to change the passphrase",
+                format!(
+                    "The old passphrase remains valid. The app will restart now.\n\n{}",
+                    e
+                ),
+            )
+            .kind(MessageDialogKind::Error)
+            .show(move |_| app_handle.restart());
+        }
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn open_change_passphrase(app_handle: tauri::AppHandle) -> tauri::Result<()> {
+    build_change_passphrase_window(&app_handle, false)
+}
+
+/// Opens the window to change the passphrase. If the keystore is still locked with the
+/// `LEGACY_PASSPHRASE`, the window asks the user to choose a passphrase of their own instead.
+pub fn build_change_passphrase_window(
+    app_handle: &tauri::AppHandle,
+    legacy_passphrase: bool,
+) -> tauri::Result<()> {
+    if let Some(window) = app_handle.get_window("change_passphrase") {
+        window.show().unwrap();
+        window.unminimize().unwrap();
+        window.set_focus().unwrap();
+    } else {
+        let _ = WindowBuilder::new(
+            app_handle,
+            "change_passphrase",
+            tauri::WindowUrl::App(
+                std::path::PathBuf::from("kangaroo_assets").join("change_passphrase.html"),
+            ),
+        )
+        .title("Change Passphrase")
+        .inner_size(500.0, 380.0)
+        .center()
+        .minimizable(false)
+        .initialization_script(&format!(
+            "window.__KANGAROO_LEGACY_PASSPHRASE__ = {};",
+            match legacy_passphrase {
+                true => format!("{:?}", LEGACY_PASSPHRASE),
+                false => String::from("null"),
+            }
+        ))
+        .build();
+    }
+    Ok(())
+}
@@ -1,32 +1,106 @@
-use tauri::{Manager, WindowBuilder};
+use std::collections::HashMap;
 
-use crate::app_state::{filesystem::Profile, AppState};
+use tauri::{AppHandle, Manager, WindowBuilder};
 
+use crate::{
+    app_state::{
+        filesystem::{AppFileSystem, Profile},
+        metadata::read_profile_metadata,
+    },
+    process::shutdown::shutdown,
+};
+
+// These take the AppFileSystem instead of the AppState so that profiles can
+// also be switched if the launch failed
+
+#[tauri::command]
+pub fn get_existing_profiles(fs: tauri::State<'_, AppFileSystem>) -> Result<Vec<Profile>, String> {
+    fs.get_existing_profiles()
+}
+
+/// Returns the data directory of an older version each migrated profile came from
 #[tauri::command]
-pub fn get_existing_profiles(state: tauri::State<'_, AppState>) -> Result<Vec<Profile>, String> {
-    state.fs.get_existing_profiles()
+pub fn get_profiles_migrated_from(
+    fs: tauri::State<'_, AppFileSystem>,
+) -> Result<HashMap<Profile, String>, String> {
+    Ok(fs
+        .get_existing_profiles()?
+        .into_iter()
+        .filter_map(|profile| {
+            read_profile_metadata(&fs.app_data_dir.join(&profile))
+                .migrated_from
+                .map(|migrated_from| (profile, migrated_from))
+        })
+        .collect())
 }
 
 #[tauri::command]
-pub fn get_active_profile(state: tauri::State<'_, AppState>) -> Profile {
-    state.fs.get_active_profile()
+pub fn get_active_profile(fs: tauri::State<'_, AppFileSystem>) -> Profile {
+    fs.get_active_profile()
 }
 
 #[tauri::command]
 pub fn set_active_profile(
-    state: tauri::State<'_, AppState>,
+    fs: tauri::State<'_, AppFileSystem>,
     profile: String,
 ) -> Result<(), String> {
-    state.fs.set_active_profile(&profile)
+    fs.set_active_profile(&profile)
 }
 
 #[tauri::command]
 pub fn set_profile_network_seed(
-    state: tauri::State<'_, AppState>,
+    fs: tauri::State<'_, AppFileSystem>,
     profile: String,
     network_seed: Option<String>,
 ) -> Result<(), String> {
-    state.fs.set_profile_network_seed(profile, network_seed)
+    fs.set_profile_network_seed(profile, network_seed)
+}
+
+/// Deletes the conductor data of the active profile, e.g. if its databases are corrupted, and
+/// restarts the app. The keystore is kept, so that the app gets installed under the same agent
+/// key again on the next launch. That key is read from the profile metadata or the keystore
+/// then, so this also works if the launch failed and there is no `AppState`.
+#[tauri::command]
+pub async fn reset_profile_conductor(app_handle: AppHandle) -> Result<(), String> {
+    reset_profile_conductor_and_restart(app_handle).await
+}
+
+pub async fn reset_profile_conductor_and_restart(app_handle: AppHandle) -> Result<(), String> {
+    stop_and_reset_conductor_data(&app_handle).await?;
+    app_handle.restart();
+    Ok(())
+}
+
+/// Deletes the conductor data of the active profile like `reset_profile_conductor`, but also
+/// forgets the agent key the app was installed under, so that it gets installed under a newly
+/// generated one on the next launch. The old key stays in the keystore.
+#[tauri::command]
+pub async fn reset_profile_identity(app_handle: AppHandle) -> Result<(), String> {
+    reset_profile_identity_and_restart(app_handle).await
+}
+
+pub async fn reset_profile_identity_and_restart(app_handle: AppHandle) -> Result<(), String> {
+    stop_and_reset_conductor_data(&app_handle).await?;
+    app_handle
+        .state::<AppFileSystem>()
+        .update_profile_metadata(|metadata| {
+            metadata.agent_pub_key = None;
+            metadata.new_identity_requested = true;
+        })
+        .map_err(|e| format!("Failed to forget the agent key: {}", e))?;
+    app_handle.restart();
+    Ok(())
+}
+
+async fn stop_and_reset_conductor_data(app_handle: &AppHandle) -> Result<(), String> {
+    log::warn!("Resetting the conductor data. Stopping holochain and lair-keystore...");
+    tauri::async_runtime::spawn_blocking(shutdown)
+        .await
+        .map_err(|e| format!("Failed to stop holochain: {}", e))?;
+    app_handle
+        .state::<AppFileSystem>()
+        .reset_conductor_data()
+        .map_err(|e| format!("Failed to reset the conductor data: {}", e))
 }
 
 #[tauri::command]
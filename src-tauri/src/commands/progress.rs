@@ -0,0 +1,9 @@
+use crate::app::splash::{LaunchProgress, LaunchProgressEvent};
+
+/// Returns the launch stages reported so far, for the splash window to catch up on
+#[tauri::command]
+pub fn get_launch_progress(
+    progress: tauri::State<'_, LaunchProgress>,
+) -> Result<Vec<LaunchProgressEvent>, String> {
+    Ok(progress.events())
+}
@@ -1,7 +1,9 @@
+use crate::process::shutdown::shutdown_and_restart;
+
 // restarts the Holochain Launcher
 #[tauri::command]
 pub fn restart(app_handle: tauri::AppHandle) -> Result<(), String> {
     log::warn!("A Restart of the app has been requested. Restarting...");
-    app_handle.restart();
+    shutdown_and_restart(app_handle);
     Ok(())
 }
@@ -0,0 +1,81 @@
+use tauri::{Manager, WindowBuilder};
+
+use crate::{
+    app_state::filesystem::AppFileSystem,
+    errors::AppResult,
+    storage::{self, StorageUsage},
+};
+
+// Walking and archiving data directories takes a while, so these run off the main thread.
+// They take the AppFileSystem so that storage can also be freed if the launch failed.
+
+async fn run_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> AppResult<T> + Send + 'static,
+) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Lists the data directories of all versions of the app and their profiles with their
+/// disk usage, together with the archives made of them
+#[tauri::command]
+pub async fn get_storage_usage(
+    fs: tauri::State<'_, AppFileSystem>,
+) -> Result<StorageUsage, String> {
+    let fs = fs.inner().clone();
+    run_blocking(move || storage::storage_usage(&fs)).await
+}
+
+#[tauri::command]
+pub async fn archive_storage(
+    fs: tauri::State<'_, AppFileSystem>,
+    version: String,
+    profile: Option<String>,
+) -> Result<(), String> {
+    let fs = fs.inner().clone();
+    run_blocking(move || storage::archive_storage(&fs, &version, profile.as_deref()))
+        .await
+        .map(|_| ())
+}
+
+#[tauri::command]
+pub async fn delete_storage(
+    fs: tauri::State<'_, AppFileSystem>,
+    version: String,
+    profile: Option<String>,
+) -> Result<(), String> {
+    let fs = fs.inner().clone();
+    run_blocking(move || storage::delete_storage(&fs, &version, profile.as_deref())).await
+}
+
+#[tauri::command]
+pub async fn delete_archive(
+    fs: tauri::State<'_, AppFileSystem>,
+    file_name: String,
+) -> Result<(), String> {
+    let fs = fs.inner().clone();
+    run_blocking(move || storage::delete_archive(&fs, &file_name)).await
+}
+
+#[tauri::command]
+pub fn open_storage_manager(app_handle: tauri::AppHandle) -> tauri::Result<()> {
+    if let Some(window) = app_handle.get_window("storage") {
+        window.show().unwrap();
+        window.unminimize().unwrap();
+        window.set_focus().unwrap();
+    } else {
+        let _ = WindowBuilder::new(
+            &app_handle,
+            "storage",
+            tauri::WindowUrl::App(std::path::PathBuf::from("kangaroo_assets").join("storage.html")),
+        )
+        .title("Manage Storage")
+        .inner_size(640.0, 520.0)
+        .center()
+        .minimizable(false)
+        .build();
+    }
+    Ok(())
+}
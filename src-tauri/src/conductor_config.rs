@@ -0,0 +1,161 @@
+use holochain::conductor::config::ConductorConfig;
+use serde_yaml::Value;
+
+use crate::{
+    app_state::filesystem::AppFileSystem,
+    errors::{AppError, AppResult},
+};
+
+/// Name of the file in the profile config dir whose content gets merged onto the generated conductor config
+pub const CONDUCTOR_CONFIG_OVERRIDES_FILE_NAME: &str = "conductor-config-overrides.yaml";
+
+/// Top level keys of the conductor config that the app relies on and that must not be overridden
+const MANAGED_KEYS: &[&str] = &["data_root_path", "keystore", "admin_interfaces"];
+
+/// Deep-merges the overrides file of the profile, if there is one, onto the generated conductor config
+/// and logs how the resulting config differs from the defaults of the conductor.
+pub fn apply_conductor_config_overrides(
+    fs: &AppFileSystem,
+    config: ConductorConfig,
+) -> AppResult<ConductorConfig> {
+    let overrides_path = fs
+        .profile_config_dir
+        .join(CONDUCTOR_CONFIG_OVERRIDES_FILE_NAME);
+
+    let config = match overrides_path.exists() {
+        false => config,
+        true => {
+            log::info!("Applying conductor config overrides {:?}", overrides_path);
+            let overrides_string = std::fs::read_to_string(&overrides_path)?;
+            let overrides: Value = serde_yaml::from_str(&overrides_string).map_err(|e| {
+                AppError::ConfigError(format!(
+                    "Failed to parse conductor config overrides {:?}: {}",
+                    overrides_path, e
+                ))
+            })?;
+            validate_overrides(&overrides)?;
+
+            let mut merged = to_value(&config)?;
+            merge(&mut merged, overrides);
+
+            // deserializing makes sure that the merged config is still a valid conductor config
+            serde_yaml::from_value(merged).map_err(|e| {
+                AppError::ConfigError(format!(
+                    "The conductor config overrides {:?} result in an invalid conductor config: {}",
+                    overrides_path, e
+                ))
+            })?
+        }
+    };
+
+    let mut differences = Vec::new();
+    diff(
+        "",
+        &to_value(&ConductorConfig::default())?,
+        &to_value(&config)?,
+        &mut differences,
+    );
+    log::info!(
+        "Effective conductor config, compared to the defaults:\n{}",
+        differences.join("\n")
+    );
+
+    Ok(config)
+}
+
+fn to_value(config: &ConductorConfig) -> AppResult<Value> {
+    serde_yaml::to_value(config)
+        .map_err(|e| AppError::ConfigError(format!("Failed to serialize conductor config: {}", e)))
+}
+
+fn validate_overrides(overrides: &Value) -> AppResult<()> {
+    let mapping = match overrides {
+        Value::Mapping(mapping) => mapping,
+        // an empty file
+        Value::Null => return Ok(()),
+        _ => {
+            return Err(AppError::ConfigError(String::from(
+                "The conductor config overrides must be a mapping",
+            )))
+        }
+    };
+    for key in MANAGED_KEYS {
+        if mapping.contains_key(&Value::from(*key)) {
+            return Err(AppError::ConfigError(format!(
+                "`{}` is managed by the app and can't be overridden in the conductor config overrides",
+                key
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Merges mappings key by key, any other value in the overrides replaces the base value
+fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Mapping(base), Value::Mapping(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        // an empty overrides file leaves the config untouched
+        (_, Value::Null) => {}
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// Collects the paths of the values that differ between the two configs
+fn diff(path: &str, default: &Value, effective: &Value, differences: &mut Vec<String>) {
+    match (default, effective) {
+        (Value::Mapping(default), Value::Mapping(effective)) => {
+            let keys = default
+                .iter()
+                .chain(effective.iter())
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<Value>>();
+            let mut seen = Vec::new();
+            for key in keys {
+                if seen.contains(&key) {
+                    continue;
+                }
+                let key_path = match &key {
+                    Value::String(key) if path.is_empty() => key.clone(),
+                    Value::String(key) => format!("{}.{}", path, key),
+                    key => format!("{}.{:?}", path, key),
+                };
+                let null = Value::Null;
+                diff(
+                    &key_path,
+                    default.get(&key).unwrap_or(&null),
+                    effective.get(&key).unwrap_or(&null),
+                    differences,
+                );
+                seen.push(key);
+            }
+        }
+        (default, effective) if default != effective => differences.push(format!(
+            "  {}: {} -> {}",
+            path,
+            inline_yaml(default),
+            inline_yaml(effective)
+        )),
+        _ => {}
+    }
+}
+
+fn inline_yaml(value: &Value) -> String {
+    match value {
+        Value::Null => String::from("~"),
+        Value::Mapping(_) | Value::Sequence(_) => {
+            serde_json::to_string(value).unwrap_or_else(|_| String::from("<unprintable>"))
+        }
+        value => serde_yaml::to_string(value)
+            .map(|s| s.trim_start_matches("---").trim().to_string())
+            .unwrap_or_else(|_| String::from("<unprintable>")),
+    }
+}
@@ -1,3 +1,13 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::filesystem::AppFileSystem,
+    errors::{AppError, AppResult},
+    passphrase::PassphraseProviderConfig,
+};
+
 /// name of the app. Can be changed without breaking your app.
 pub const APP_NAME: &str = "replace-me";
 
@@ -13,9 +23,6 @@ pub const WINDOW_WIDTH: f64 = 1400.0;
 /// Default window height when the app is opened
 pub const WINDOW_HEIGHT: f64 = 880.0;
 
-/// Password to the lair keystore
-pub const PASSWORD: &str = "pass";
-
 /// replace-me (optional): Depending on your application, you may want to put a network seed here or
 /// read it secretly from an environment variable. If so, replace `None` with `Some("your network seed here")`
 pub const DEFAULT_NETWORK_SEED: Option<&str> = None;
@@ -30,3 +37,336 @@ pub const BOOTSTRAP_SERVER: &str = "https://bootstrap.holo.host";
 pub const HOLOCHAIN_VERSION: &str = env!("HOLOCHAIN_VERSION");
 
 pub const LAIR_KEYSTORE_VERSION: &str = "0.4.5";
+
+/// Time in seconds the conductor gets to start up and become reachable before the launch fails
+pub const LAUNCH_TIMEOUT_SECS: u64 = 120;
+
+/// Name of the runtime configuration file, looked up both in the app config dir
+/// and in the config dir of the active profile
+pub const CONFIG_FILE_NAME: &str = "kangaroo.toml";
+
+/// Prefix of the environment variables that override the configuration files
+pub const CONFIG_ENV_PREFIX: &str = "KANGAROO_";
+
+/// Configuration resolved at runtime. Each layer overrides the previous one:
+/// 1. the compiled defaults defined in this file
+/// 2. the global `kangaroo.toml` in the app config dir
+/// 3. the `kangaroo.toml` in the config dir of the active profile
+/// 4. `KANGAROO_*` environment variables, e.g. `KANGAROO_BOOTSTRAP_SERVER`
+#[derive(Debug, Clone, Serialize)]
+pub struct KangarooConfig {
+    pub app_id: String,
+    pub window_width: f64,
+    pub window_height: f64,
+    pub bootstrap_server: String,
+    pub signaling_server: String,
+    pub network_seed: Option<String>,
+    /// Where to get the passphrase of the keystore from. Not read from the environment.
+    pub passphrase: PassphraseProviderConfig,
+    pub keystore_mode: KeystoreMode,
+    pub conductor_mode: ConductorMode,
+    pub launch_timeout_secs: u64,
+    /// Fixed port of the admin interface. A free port is picked if not set.
+    pub admin_port: Option<u16>,
+    /// Fixed port of the app interface. A free port is picked if not set.
+    pub app_port: Option<u16>,
+    /// Origins allowed to connect to the conductor in addition to the webview, e.g. a dev server.
+    /// `*` allows any origin.
+    pub dev_allowed_origins: Vec<String>,
+    /// Whether to hand the admin port to the UI. Gives the UI full control over the conductor.
+    pub expose_admin_port: bool,
+    /// Admin operations the UI may call through Tauri commands
+    pub admin_commands: Vec<AdminCommand>,
+    /// Roles of the happ whose DNAs require a membrane proof at install
+    pub membrane_proof_roles: Vec<String>,
+}
+
+/// Curated admin operation exposed to the UI as a Tauri command of the same name,
+/// if enabled in `admin_commands`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminCommand {
+    AppInfo,
+    DeleteCloneCell,
+}
+
+impl std::str::FromStr for AdminCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "app_info" => Ok(AdminCommand::AppInfo),
+            "delete_clone_cell" => Ok(AdminCommand::DeleteCloneCell),
+            _ => Err(String::from("expected `app_info` or `delete_clone_cell`")),
+        }
+    }
+}
+
+/// How the lair keystore is run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeystoreMode {
+    /// As the lair-keystore sidecar binary
+    Sidecar,
+    /// Inside of this process. The lair-keystore binary is not needed in this mode.
+    InProc,
+}
+
+/// How the holochain conductor is run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConductorMode {
+    /// As the holochain sidecar binary
+    Sidecar,
+    /// Inside of this process. Requires the `embedded-conductor` feature.
+    Embedded,
+}
+
+impl std::str::FromStr for ConductorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sidecar" => Ok(ConductorMode::Sidecar),
+            "embedded" => Ok(ConductorMode::Embedded),
+            _ => Err(String::from("expected `sidecar` or `embedded`")),
+        }
+    }
+}
+
+impl std::str::FromStr for KeystoreMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sidecar" => Ok(KeystoreMode::Sidecar),
+            "in_proc" => Ok(KeystoreMode::InProc),
+            _ => Err(String::from("expected `sidecar` or `in_proc`")),
+        }
+    }
+}
+
+/// The global and the profile config file, in the order in which they are applied
+fn config_file_paths(fs: &AppFileSystem) -> [PathBuf; 2] {
+    [
+        fs.app_config_dir.join(CONFIG_FILE_NAME),
+        fs.profile_config_dir.join(CONFIG_FILE_NAME),
+    ]
+}
+
+impl Default for KangarooConfig {
+    fn default() -> Self {
+        KangarooConfig {
+            app_id: APP_ID.to_string(),
+            window_width: WINDOW_WIDTH,
+            window_height: WINDOW_HEIGHT,
+            bootstrap_server: BOOTSTRAP_SERVER.to_string(),
+            signaling_server: SIGNALING_SERVER.to_string(),
+            network_seed: DEFAULT_NETWORK_SEED.map(String::from),
+            passphrase: PassphraseProviderConfig::default(),
+            keystore_mode: KeystoreMode::Sidecar,
+            conductor_mode: ConductorMode::Sidecar,
+            launch_timeout_secs: LAUNCH_TIMEOUT_SECS,
+            admin_port: None,
+            app_port: None,
+            dev_allowed_origins: Vec::new(),
+            expose_admin_port: false,
+            admin_commands: Vec::new(),
+            membrane_proof_roles: Vec::new(),
+        }
+    }
+}
+
+/// A single configuration layer as read from a `kangaroo.toml` file or from the environment.
+/// Fields that are not set leave the value of the layer below untouched.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KangarooConfigLayer {
+    pub app_id: Option<String>,
+    pub window_width: Option<f64>,
+    pub window_height: Option<f64>,
+    pub bootstrap_server: Option<String>,
+    pub signaling_server: Option<String>,
+    pub network_seed: Option<String>,
+    pub passphrase: Option<PassphraseProviderConfig>,
+    pub keystore_mode: Option<KeystoreMode>,
+    pub conductor_mode: Option<ConductorMode>,
+    pub launch_timeout_secs: Option<u64>,
+    pub admin_port: Option<u16>,
+    pub app_port: Option<u16>,
+    pub dev_allowed_origins: Option<Vec<String>>,
+    pub expose_admin_port: Option<bool>,
+    pub admin_commands: Option<Vec<AdminCommand>>,
+    pub membrane_proof_roles: Option<Vec<String>>,
+}
+
+impl KangarooConfig {
+    /// Resolves the configuration for the profile of the given filesystem. This happens before the
+    /// logs are set up, since the log file is named after the app id, so `log_resolved` logs it.
+    pub fn load(fs: &AppFileSystem) -> AppResult<KangarooConfig> {
+        let mut config = KangarooConfig::default();
+
+        for config_path in config_file_paths(fs) {
+            if let Some(layer) = KangarooConfigLayer::from_file(&config_path)? {
+                config.apply(layer);
+            }
+        }
+
+        config.apply(KangarooConfigLayer::from_env()?);
+
+        Ok(config)
+    }
+
+    /// Logs the config files that were applied and the resolved config
+    pub fn log_resolved(&self, fs: &AppFileSystem) {
+        for config_path in config_file_paths(fs) {
+            if config_path.exists() {
+                log::info!("Applied config file {:?}", config_path);
+            }
+        }
+        log::info!("Resolved config: {:?}", self);
+    }
+
+    fn apply(&mut self, layer: KangarooConfigLayer) {
+        if let Some(app_id) = layer.app_id {
+            self.app_id = app_id;
+        }
+        if let Some(window_width) = layer.window_width {
+            self.window_width = window_width;
+        }
+        if let Some(window_height) = layer.window_height {
+            self.window_height = window_height;
+        }
+        if let Some(bootstrap_server) = layer.bootstrap_server {
+            self.bootstrap_server = bootstrap_server;
+        }
+        if let Some(signaling_server) = layer.signaling_server {
+            self.signaling_server = signaling_server;
+        }
+        if let Some(network_seed) = layer.network_seed {
+            self.network_seed = Some(network_seed);
+        }
+        if let Some(passphrase) = layer.passphrase {
+            self.passphrase = passphrase;
+        }
+        if let Some(keystore_mode) = layer.keystore_mode {
+            self.keystore_mode = keystore_mode;
+        }
+        if let Some(conductor_mode) = layer.conductor_mode {
+            self.conductor_mode = conductor_mode;
+        }
+        if let Some(launch_timeout_secs) = layer.launch_timeout_secs {
+            self.launch_timeout_secs = launch_timeout_secs;
+        }
+        if let Some(admin_port) = layer.admin_port {
+            self.admin_port = Some(admin_port);
+        }
+        if let Some(app_port) = layer.app_port {
+            self.app_port = Some(app_port);
+        }
+        if let Some(dev_allowed_origins) = layer.dev_allowed_origins {
+            self.dev_allowed_origins = dev_allowed_origins;
+        }
+        if let Some(expose_admin_port) = layer.expose_admin_port {
+            self.expose_admin_port = expose_admin_port;
+        }
+        if let Some(admin_commands) = layer.admin_commands {
+            self.admin_commands = admin_commands;
+        }
+        if let Some(membrane_proof_roles) = layer.membrane_proof_roles {
+            self.membrane_proof_roles = membrane_proof_roles;
+        }
+    }
+}
+
+impl KangarooConfigLayer {
+    /// Reads a layer from a toml file. Returns `None` if the file does not exist.
+    pub fn from_file(path: &Path) -> AppResult<Option<KangarooConfigLayer>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        let layer = toml::from_str(&content).map_err(|e| {
+            AppError::ConfigError(format!("Failed to parse config file {:?}: {}", path, e))
+        })?;
+        Ok(Some(layer))
+    }
+
+    /// Reads a layer from the `KANGAROO_*` environment variables
+    pub fn from_env() -> AppResult<KangarooConfigLayer> {
+        Ok(KangarooConfigLayer {
+            app_id: read_env_var("APP_ID"),
+            window_width: read_env_var("WINDOW_WIDTH")
+                .map(|w| parse_env_var("WINDOW_WIDTH", &w))
+                .transpose()?,
+            window_height: read_env_var("WINDOW_HEIGHT")
+                .map(|h| parse_env_var("WINDOW_HEIGHT", &h))
+                .transpose()?,
+            bootstrap_server: read_env_var("BOOTSTRAP_SERVER"),
+            signaling_server: read_env_var("SIGNALING_SERVER"),
+            network_seed: read_env_var("NETWORK_SEED"),
+            passphrase: None,
+            keystore_mode: read_env_var("KEYSTORE_MODE")
+                .map(|m| parse_env_var("KEYSTORE_MODE", &m))
+                .transpose()?,
+            conductor_mode: read_env_var("CONDUCTOR_MODE")
+                .map(|m| parse_env_var("CONDUCTOR_MODE", &m))
+                .transpose()?,
+            launch_timeout_secs: read_env_var("LAUNCH_TIMEOUT_SECS")
+                .map(|t| parse_env_var("LAUNCH_TIMEOUT_SECS", &t))
+                .transpose()?,
+            admin_port: read_env_var("ADMIN_PORT")
+                .map(|p| parse_env_var("ADMIN_PORT", &p))
+                .transpose()?,
+            app_port: read_env_var("APP_PORT")
+                .map(|p| parse_env_var("APP_PORT", &p))
+                .transpose()?,
+            // comma separated
+            dev_allowed_origins: read_env_var("DEV_ALLOWED_ORIGINS").map(|origins| {
+                origins
+                    .split(',')
+                    .map(|origin| origin.trim().to_string())
+                    .filter(|origin| !origin.is_empty())
+                    .collect()
+            }),
+            expose_admin_port: read_env_var("EXPOSE_ADMIN_PORT")
+                .map(|e| parse_env_var("EXPOSE_ADMIN_PORT", &e))
+                .transpose()?,
+            // comma separated
+            admin_commands: read_env_var("ADMIN_COMMANDS")
+                .map(|commands| {
+                    commands
+                        .split(',')
+                        .map(|command| command.trim())
+                        .filter(|command| !command.is_empty())
+                        .map(|command| parse_env_var("ADMIN_COMMANDS", command))
+                        .collect::<AppResult<Vec<AdminCommand>>>()
+                })
+                .transpose()?,
+            // comma separated
+            membrane_proof_roles: read_env_var("MEMBRANE_PROOF_ROLES").map(|roles| {
+                roles
+                    .split(',')
+                    .map(|role| role.trim().to_string())
+                    .filter(|role| !role.is_empty())
+                    .collect()
+            }),
+        })
+    }
+}
+
+fn read_env_var(name: &str) -> Option<String> {
+    std::env::var(format!("{}{}", CONFIG_ENV_PREFIX, name)).ok()
+}
+
+fn parse_env_var<T: std::str::FromStr>(name: &str, value: &str) -> AppResult<T>
+where
+    T::Err: std::fmt::Display,
+{
+    value.parse::<T>().map_err(|e| {
+        AppError::ConfigError(format!(
+            "Invalid value for {}{}: `{}` ({})",
+            CONFIG_ENV_PREFIX, name, value, e
+        ))
+    })
+}
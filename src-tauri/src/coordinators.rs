@@ -0,0 +1,220 @@
+use holochain::prelude::{
+    AppBundle, CellInfo, CoordinatorBundle, CoordinatorSource, CoordinatorZomeDef, DnaBundle,
+    DnaManifest, DnaModifiersOpt, UpdateCoordinatorsPayload, ZomeName,
+};
+use holochain_client::AdminWebsocket;
+use mr_bundle::Location;
+
+use crate::{
+    app::splash::{LaunchProgress, LaunchStage},
+    app_state::{filesystem::AppFileSystem, metadata::CoordinatorUpdateRecord},
+    errors::{AppError, AppResult},
+};
+
+/// Number of coordinator updates kept in the profile metadata
+const MAX_COORDINATOR_UPDATE_RECORDS: usize = 50;
+
+/// Compares the coordinator zomes of the bundled happ with the ones of the installed app and
+/// updates the coordinators of every DNA where they differ, including the DNAs of clone cells.
+/// A failed update is logged and recorded in the profile metadata, but doesn't stop the launch
+/// since the installed coordinators keep working.
+pub async fn update_coordinators_if_necessary(
+    fs: &AppFileSystem,
+    app_id: &String,
+    app_bundle: &AppBundle,
+    admin_ws: &AdminWebsocket,
+    progress: &LaunchProgress,
+) -> AppResult<()> {
+    let apps = admin_ws
+        .list_apps(None)
+        .await
+        .map_err(|e| AppError::ConductorApiError(e))?;
+    let app_info = match apps.into_iter().find(|app| &app.installed_app_id == app_id) {
+        Some(app_info) => app_info,
+        None => return Ok(()),
+    };
+
+    progress.start(LaunchStage::CoordinatorUpdate);
+
+    let mut records = Vec::new();
+    for role in app_bundle.manifest().app_roles() {
+        // clone cells have DNA hashes of their own but run the coordinators of their role
+        let dna_hashes = app_info
+            .cell_info
+            .get(&role.name)
+            .map(|cells| {
+                cells
+                    .iter()
+                    .filter_map(|cell| match cell {
+                        CellInfo::Provisioned(cell) => Some(cell.cell_id.dna_hash().clone()),
+                        CellInfo::Cloned(cell) => Some(cell.cell_id.dna_hash().clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        if dna_hashes.is_empty() {
+            log::warn!(
+                "[COORDINATORS] No provisioned or cloned cell for role {}, skipping it",
+                role.name
+            );
+            continue;
+        }
+
+        let (bundled_coordinator_zomes, coordinator_bundle) =
+            match bundled_coordinators(app_bundle, &role.dna.location).await {
+                Ok(coordinators) => coordinators,
+                Err(e) => {
+                    log::error!(
+                        "[COORDINATORS] Failed to read the bundled coordinators of role {}: {}",
+                        role.name,
+                        e
+                    );
+                    continue;
+                }
+            };
+        let zomes = bundled_coordinator_zomes
+            .iter()
+            .map(|(zome_name, _)| zome_name.to_string())
+            .collect::<Vec<String>>();
+
+        for dna_hash in dna_hashes {
+            let installed_dna_def = match admin_ws.get_dna_definition(dna_hash.clone()).await {
+                Ok(dna_def) => dna_def,
+                Err(e) => {
+                    log::error!(
+                        "[COORDINATORS] Failed to get the definition of DNA {} of role {}, skipping it: {:?}",
+                        dna_hash,
+                        role.name,
+                        e
+                    );
+                    continue;
+                }
+            };
+            if installed_dna_def.coordinator_zomes == bundled_coordinator_zomes {
+                continue;
+            }
+
+            log::info!(
+                "[COORDINATORS] Coordinators of DNA {} of role {} differ from the bundled ones, updating them to {:?}",
+                dna_hash,
+                role.name,
+                zomes
+            );
+
+            let error = match admin_ws
+                .update_coordinators(UpdateCoordinatorsPayload {
+                    dna_hash: dna_hash.clone(),
+                    source: CoordinatorSource::Bundle(Box::new(coordinator_bundle.clone())),
+                })
+                .await
+            {
+                Ok(()) => {
+                    log::info!(
+                        "[COORDINATORS] Updated the coordinators of DNA {} of role {}",
+                        dna_hash,
+                        role.name
+                    );
+                    None
+                }
+                Err(e) => {
+                    log::error!(
+                        "[COORDINATORS] Failed to update the coordinators of DNA {} of role {}: {:?}",
+                        dna_hash,
+                        role.name,
+                        e
+                    );
+                    Some(format!("{:?}", e))
+                }
+            };
+
+            records.push(CoordinatorUpdateRecord {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                role_name: role.name.clone(),
+                dna_hash: dna_hash.to_string(),
+                zomes: zomes.clone(),
+                error,
+            });
+        }
+    }
+
+    if !records.is_empty() {
+        fs.update_profile_metadata(|metadata| {
+            add_records(&mut metadata.coordinator_updates, records)
+        })?;
+    }
+
+    progress.finish(LaunchStage::CoordinatorUpdate);
+
+    Ok(())
+}
+
+/// Adds the records of this launch. An update failing the same way as the last time it was
+/// attempted only refreshes the timestamp of the existing record, so that retrying on every
+/// launch doesn't grow the list, which is capped to the most recent records on top of that.
+fn add_records(existing: &mut Vec<CoordinatorUpdateRecord>, records: Vec<CoordinatorUpdateRecord>) {
+    for record in records {
+        let previous = existing
+            .iter_mut()
+            .rev()
+            .find(|previous| previous.dna_hash == record.dna_hash);
+        match previous {
+            Some(previous)
+                if record.error.is_some()
+                    && previous.error == record.error
+                    && previous.zomes == record.zomes =>
+            {
+                previous.timestamp = record.timestamp;
+            }
+            _ => existing.push(record),
+        }
+    }
+    if existing.len() > MAX_COORDINATOR_UPDATE_RECORDS {
+        existing.drain(..existing.len() - MAX_COORDINATOR_UPDATE_RECORDS);
+    }
+}
+
+/// Reads the coordinator zomes of the DNA at the given location of the app bundle, both as
+/// definitions to compare with the installed ones and as a bundle to update them with
+async fn bundled_coordinators(
+    app_bundle: &AppBundle,
+    location: &Option<Location>,
+) -> Result<(Vec<(ZomeName, CoordinatorZomeDef)>, CoordinatorBundle), String> {
+    let dna_path = match location {
+        Some(Location::Bundled(path)) => path,
+        _ => return Err(String::from("the DNA is not bundled with the happ")),
+    };
+    let dna_bytes = app_bundle
+        .bundled_resources()
+        .get(dna_path)
+        .ok_or(format!("{:?} is missing from the happ", dna_path))?;
+    let dna_bundle = DnaBundle::decode(dna_bytes).map_err(|e| e.to_string())?;
+
+    let coordinator_manifest = match dna_bundle.manifest() {
+        DnaManifest::V1(manifest) => manifest.coordinator.clone(),
+    };
+    let resources = coordinator_manifest
+        .zomes
+        .iter()
+        .filter_map(|zome| match &zome.location {
+            Location::Bundled(path) => dna_bundle
+                .bundled_resources()
+                .get(path)
+                .map(|bytes| (path.clone(), bytes.clone())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let (dna_file, _) = dna_bundle
+        .into_dna_file(DnaModifiersOpt::none())
+        .await
+        .map_err(|e| e.to_string())?;
+    let coordinator_bundle =
+        CoordinatorBundle::new(coordinator_manifest, resources, Default::default())
+            .map_err(|e| e.to_string())?;
+
+    Ok((
+        dna_file.dna_def().coordinator_zomes.clone(),
+        coordinator_bundle,
+    ))
+}
@@ -3,12 +3,17 @@ use holochain_client::ConductorApiError;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::config::AdminCommand;
+
 #[allow(dead_code)]
 #[derive(Debug, Error)]
 pub enum AppError {
     #[error("Filesystem error: `{0}`")]
     FileSystemError(String),
 
+    #[error("Config error: `{0}`")]
+    ConfigError(String),
+
     #[error("Applets UI server error: `{0}`")]
     AppletsUIServerError(String),
 
@@ -50,6 +55,15 @@ pub enum AppError {
     #[error("Error signing zome call: `{0}`")]
     SignZomeCallError(String),
 
+    #[error("Membrane proof error: `{0}`")]
+    MembraneProofError(String),
+
+    #[error("Clone cell error: `{0}`")]
+    CloneCellError(String),
+
+    #[error("The admin command `{0:?}` is not enabled in the config of this app")]
+    AdminCommandNotEnabled(AdminCommand),
+
     #[error(transparent)]
     LairKeystoreError(#[from] LairKeystoreError),
 
@@ -57,6 +71,13 @@ pub enum AppError {
     LaunchHolochainError(#[from] LaunchHolochainError),
 }
 
+// Lets commands return an `AppResult`, the UI receives the error message
+impl Serialize for AppError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
 pub type AppResult<T> = Result<T, AppError>;
 
 #[derive(Error, Serialize, Deserialize, Debug, Clone)]
@@ -79,6 +100,9 @@ pub enum LaunchHolochainError {
     #[error("Failed to overwrite config: `{0}`")]
     FailedToOverwriteConfig(String),
 
+    #[error("Could not find a free port")]
+    NoFreePort,
+
     #[error("Failed to create sidecar binary command: `{0}`")]
     SidecarBinaryCommandError(String),
 
@@ -94,8 +118,26 @@ pub enum InitializeConductorError {
     #[error("Could not connect to the database of the conductor: `{0}`")]
     SqliteError(String),
 
+    #[error("The database of the conductor is locked by another process: `{0}`")]
+    DatabaseLocked(String),
+
     #[error("Address already in use: `{0}`")]
     AddressAlreadyInUse(String),
+
+    #[error("Could not connect to the keystore: `{0}`")]
+    KeystoreError(String),
+
+    #[error("Invalid conductor config: `{0}`")]
+    InvalidConfig(String),
+
+    #[error("Permission denied: `{0}`")]
+    PermissionDenied(String),
+
+    #[error("No space left on device: `{0}`")]
+    DiskFull(String),
+
+    #[error("The conductor exited before it was ready: `{0}`")]
+    ProcessExitedEarly(String),
 }
 
 #[derive(Error, Serialize, Deserialize, Debug, Clone)]
@@ -110,6 +152,15 @@ pub enum LairKeystoreError {
     #[error("Incorrect password")]
     IncorrectPassword,
 
+    #[error("Passphrase unavailable: `{0}`")]
+    PassphraseUnavailable(String),
+
+    #[error("lair-keystore did not start within {0} seconds")]
+    StartupTimeout(u64),
+
+    #[error("lair-keystore exited before it was ready: `{0}`")]
+    ProcessExitedEarly(String),
+
     #[error("Failed to create LairClient: `{0}`")]
     ErrorCreatingLairClient(String),
 
@@ -130,6 +181,9 @@ pub enum LairKeystoreError {
 
     #[error("Failed to spawn MetaLairClient: `{0}`")]
     SpawnMetaLairClientError(String),
+
+    #[error("Failed to list the keystore entries: `{0}`")]
+    ListEntriesError(String),
 }
 
 #[derive(Error, Debug, Serialize, Deserialize, Clone)]
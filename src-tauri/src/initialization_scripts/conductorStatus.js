@@ -0,0 +1,31 @@
+// Shows a banner on top of the app while the conductor is being restarted after a crash,
+// based on the "conductor-status" events emitted by the conductor supervisor
+
+window.addEventListener("DOMContentLoaded", () => {
+  const banner = document.createElement("div");
+  banner.style.cssText =
+    "display: none; position: fixed; top: 0; left: 0; right: 0; z-index: 2147483647; padding: 8px;" +
+    "text-align: center; font-family: sans-serif; font-size: 14px; color: white; background: #b15d00;";
+  document.body.appendChild(banner);
+
+  window.__TAURI__.event.listen("conductor-status", (event) => {
+    const status = event.payload;
+    switch (status.status) {
+      case "running":
+        banner.style.display = "none";
+        // the reloaded page fetches a fresh app token to connect to the restarted conductor with
+        window.location.reload();
+        break;
+      case "reconnecting":
+        banner.innerText = `Holochain stopped unexpectedly. Reconnecting (attempt ${status.attempt})...`;
+        banner.style.background = "#b15d00";
+        banner.style.display = "block";
+        break;
+      case "failed":
+        banner.innerText = `Holochain could not be restarted: ${status.reason}. Please restart the app.`;
+        banner.style.background = "#a30000";
+        banner.style.display = "block";
+        break;
+    }
+  });
+});
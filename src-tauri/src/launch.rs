@@ -1,5 +1,12 @@
-use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
+#[cfg(feature = "embedded-conductor")]
+use holochain::conductor::ConductorHandle;
 use holochain::{
     conductor::{
         api::{AdminInterfaceConfig, InterfaceDriver},
@@ -10,24 +17,68 @@ use holochain::{
         AppBundle,
     },
 };
-use holochain_client::{AdminWebsocket, InstallAppPayload};
-use holochain_keystore::MetaLairClient;
-use holochain_types::websocket::AllowedOrigins;
+use holochain_client::{AdminWebsocket, AgentPubKey, InstallAppPayload};
+use holochain_keystore::{lair_keystore_api::prelude::LairEntryInfo, MetaLairClient};
+use holochain_types::{prelude::AgentPubKeyB64, websocket::AllowedOrigins};
 use tauri::api::process::Command;
+use url2::Url2;
 
 use crate::{
+    app::splash::{LaunchProgress, LaunchStage},
     app_state::filesystem::AppFileSystem,
-    config::{APP_ID, BOOTSTRAP_SERVER, DEFAULT_NETWORK_SEED, HOLOCHAIN_VERSION, SIGNALING_SERVER},
-    errors::{AppError, AppResult, LairKeystoreError, LaunchHolochainError},
+    conductor_config::apply_conductor_config_overrides,
+    config::{ConductorMode, KangarooConfig, KeystoreMode, HOLOCHAIN_VERSION},
+    coordinators::update_coordinators_if_necessary,
+    errors::{
+        AppError, AppResult, InitializeConductorError, LairKeystoreError, LaunchHolochainError,
+    },
+    membrane_proofs::{is_genesis_failure, to_membrane_proof, MembraneProofProvider},
+    passphrase::{PassphraseMode, PassphraseProvider, LEGACY_PASSPHRASE},
     process::{
         conductor::launch_holochain_process,
-        lair::{initialize_keystore, launch_lair_keystore_process},
+        lair::{initialize_keystore, launch_lair_keystore_process, spawn_in_proc_lair_keystore},
+        readiness::poll_until_ready,
+        supervisor::SupervisedConductor,
     },
+    rekey::recover_interrupted_passphrase_change,
 };
 
-pub async fn launch(fs: &AppFileSystem, password: String) -> AppResult<(MetaLairClient, u16, u16)> {
+#[cfg(feature = "embedded-conductor")]
+use crate::process::{embedded::launch_embedded_conductor, shutdown::register_embedded_conductor};
+
+/// Number of ports tried for the admin and app interfaces before giving up
+const MAX_PORT_ATTEMPTS: u32 = 3;
+
+/// Handles to and ports of the running keystore and conductor
+pub struct LaunchedHolochain {
+    pub meta_lair_client: MetaLairClient,
+    pub lair_url: Url2,
+    pub app_port: u16,
+    pub admin_port: u16,
+    /// Whether the keystore was unlocked with the `LEGACY_PASSPHRASE`, which the user should
+    /// replace with one of their own
+    pub legacy_passphrase: bool,
+    /// Port of an app interface attached by an earlier version that allows any origin to connect
+    pub unrestricted_app_interface: Option<u16>,
+    /// Only set if the conductor runs as a sidecar
+    pub conductor_process: Option<SupervisedConductor>,
+    /// Only set if the conductor runs embedded in this process
+    #[cfg(feature = "embedded-conductor")]
+    pub conductor_handle: Option<ConductorHandle>,
+}
+
+pub async fn launch(
+    fs: &AppFileSystem,
+    kangaroo_config: &KangarooConfig,
+    passphrase_provider: &dyn PassphraseProvider,
+    membrane_proof_provider: &dyn MembraneProofProvider,
+    allowed_origins: AllowedOrigins,
+    progress: &LaunchProgress,
+) -> AppResult<LaunchedHolochain> {
     let log_level = log::Level::Info;
 
+    recover_interrupted_passphrase_change(fs)?;
+
     if !fs.keystore_dir().exists() {
         std::fs::create_dir_all(fs.keystore_dir())?;
     }
@@ -36,168 +87,586 @@ pub async fn launch(fs: &AppFileSystem, password: String) -> AppResult<(MetaLair
         std::fs::create_dir_all(fs.conductor_dir())?;
     }
 
-    // initialize lair keystore if necessary
-    if !fs.keystore_initialized() {
-        initialize_keystore(fs.keystore_dir(), password.clone()).await?;
-    }
-
-    // spawn lair keystore process and connect to it
-    let lair_url =
-        launch_lair_keystore_process(log_level.clone(), fs.keystore_dir(), password.clone())
-            .await?;
-
-    let meta_lair_client = holochain_keystore::lair_keystore::spawn_lair_keystore(
-        lair_url.clone(),
-        password.as_bytes().into(),
+    let (meta_lair_client, lair_url, password, legacy_passphrase) = launch_keystore(
+        fs,
+        kangaroo_config.keystore_mode,
+        passphrase_provider,
+        progress,
+        log_level,
     )
+    .await?;
+
+    let mut conductor_process = None;
+    #[cfg(feature = "embedded-conductor")]
+    let mut conductor_handle = None;
+
+    progress.start(LaunchStage::ConductorStart);
+
+    // waiting for the passphrase is not limited, only the startup of the conductor itself
+    let deadline = Instant::now() + Duration::from_secs(kangaroo_config.launch_timeout_secs);
+
+    let base_config = build_conductor_config(fs, kangaroo_config, &lair_url)?;
+
+    // another process may grab the picked admin port before the conductor binds it,
+    // in which case the conductor gets started again with a fresh port
+    let mut port_attempt = 0;
+    let admin_port = loop {
+        port_attempt += 1;
+        let admin_port = match kangaroo_config.admin_port {
+            Some(port) => port,
+            None => pick_unused_port()?,
+        };
+
+        let config = with_admin_interface(&base_config, admin_port, allowed_origins.clone());
+        let conductor_config_path = write_conductor_config(fs, &config)?;
+
+        let result = match kangaroo_config.conductor_mode {
+            ConductorMode::Sidecar => {
+                let command = Command::new_sidecar(format!("holochain-v{}", HOLOCHAIN_VERSION))
+                    .map_err(|err| {
+                        AppError::LaunchHolochainError(
+                            LaunchHolochainError::SidecarBinaryCommandError(format!("{}", err)),
+                        )
+                    })?;
+
+                launch_holochain_process(
+                    log_level,
+                    command,
+                    conductor_config_path.clone(),
+                    password.clone(),
+                    deadline,
+                )
+                .await
+                .map(|(holochain_rx, holochain_child)| {
+                    conductor_process = Some(SupervisedConductor {
+                        holochain_rx,
+                        holochain_child,
+                        conductor_config_path,
+                        admin_port,
+                        password: password.clone(),
+                        log_level,
+                        keystore_mode: kangaroo_config.keystore_mode,
+                        keystore_dir: fs.keystore_dir(),
+                        lair_url: lair_url.clone(),
+                        launch_timeout: Duration::from_secs(kangaroo_config.launch_timeout_secs),
+                    });
+                })
+            }
+            #[cfg(feature = "embedded-conductor")]
+            ConductorMode::Embedded => launch_embedded_conductor(
+                config,
+                meta_lair_client.clone(),
+                password.clone(),
+                deadline,
+            )
+            .await
+            .map(|handle| {
+                register_embedded_conductor(handle.clone());
+                conductor_handle = Some(handle);
+            }),
+            #[cfg(not(feature = "embedded-conductor"))]
+            ConductorMode::Embedded => {
+                return Err(AppError::ConfigError(String::from(
+                    "conductor_mode \"embedded\" requires the app to be built with the `embedded-conductor` feature",
+                )));
+            }
+        };
+
+        match result {
+            Ok(()) => break admin_port,
+            Err(AppError::LaunchHolochainError(
+                LaunchHolochainError::CouldNotInitializeConductor(
+                    InitializeConductorError::AddressAlreadyInUse(e),
+                ),
+            )) if kangaroo_config.admin_port.is_none() && port_attempt < MAX_PORT_ATTEMPTS => {
+                log::warn!(
+                    "Admin port {} was taken before the conductor could bind it ({}/{}): {}. Retrying with another port.",
+                    admin_port,
+                    port_attempt,
+                    MAX_PORT_ATTEMPTS,
+                    e
+                );
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    // the admin interface may not be bound yet right after the conductor reports to be ready
+    let mut admin_ws = poll_until_ready("Admin websocket", deadline, || {
+        AdminWebsocket::connect(SocketAddr::from(([127, 0, 0, 1], admin_port)))
+    })
     .await
-    .map_err(|e| LairKeystoreError::SpawnMetaLairClientError(format!("{}", e)))?;
+    .map_err(LaunchHolochainError::CouldNotConnectToConductor)?;
+
+    progress.finish(LaunchStage::ConductorStart);
+    progress.start(LaunchStage::InterfaceAttach);
+
+    let mut unrestricted_app_interface = None;
+    let app_port = {
+        let app_interfaces = admin_ws.list_app_interfaces().await.map_err(|e| {
+            LaunchHolochainError::CouldNotConnectToConductor(format!(
+                "Could not list app interfaces: {:?}",
+                e
+            ))
+        })?;
+
+        // earlier versions of the app attached interfaces allowing any origin, which stay attached
+        // in the conductor state since holochain 0.3 has no way to detach them. Only resetting the
+        // conductor data gets rid of them, which the user is offered once the app is running.
+        if allowed_origins != AllowedOrigins::Any {
+            if let Some(interface) = app_interfaces
+                .iter()
+                .find(|interface| interface.allowed_origins == AllowedOrigins::Any)
+            {
+                log::warn!(
+                    "The app interface at port {} allows any origin to connect",
+                    interface.port
+                );
+                unrestricted_app_interface = Some(interface.port);
+            }
+        }
+
+        // interfaces attached by earlier versions of the app may allow other origins
+        let existing_port = app_interfaces
+            .iter()
+            .filter(|interface| interface.allowed_origins == allowed_origins)
+            .find(|interface| match kangaroo_config.app_port {
+                Some(port) => interface.port == port,
+                None => true,
+            })
+            .map(|interface| interface.port);
+
+        match existing_port {
+            Some(port) => port,
+            None => attach_app_interface(&admin_ws, kangaroo_config, allowed_origins).await?,
+        }
+    };
+
+    progress.finish(LaunchStage::InterfaceAttach);
 
-    // write conductor config to file
+    let network_seed = match fs.read_profile_network_seed() {
+        Some(seed) => Some(seed),
+        None => kangaroo_config.network_seed.clone(),
+    };
+
+    install_app_if_necessary(
+        fs,
+        &kangaroo_config.app_id,
+        network_seed,
+        &kangaroo_config.membrane_proof_roles,
+        membrane_proof_provider,
+        &meta_lair_client,
+        &mut admin_ws,
+        progress,
+    )
+    .await?;
+
+    Ok(LaunchedHolochain {
+        meta_lair_client,
+        lair_url,
+        app_port,
+        admin_port,
+        legacy_passphrase,
+        unrestricted_app_interface,
+        conductor_process,
+        #[cfg(feature = "embedded-conductor")]
+        conductor_handle,
+    })
+}
 
+/// Generates the conductor config and merges the overrides of the profile onto it.
+/// The admin interface is set with `with_admin_interface` for every attempt to start the conductor.
+fn build_conductor_config(
+    fs: &AppFileSystem,
+    kangaroo_config: &KangarooConfig,
+    lair_url: &Url2,
+) -> AppResult<ConductorConfig> {
     let mut config = ConductorConfig::default();
     config.data_root_path = Some(fs.conductor_dir().into());
     config.keystore = KeystoreConfig::LairServer {
-        connection_url: lair_url,
+        connection_url: lair_url.clone(),
     };
 
-    let admin_port = portpicker::pick_unused_port().expect("Cannot find any unused port");
-
-    config.admin_interfaces = Some(vec![AdminInterfaceConfig {
-        driver: InterfaceDriver::Websocket {
-            port: admin_port.clone(),
-            allowed_origins: AllowedOrigins::Any,
-        },
-    }]);
-
     let mut network_config = KitsuneP2pConfig::default();
-    network_config.bootstrap_service = Some(url2::url2!("{}", BOOTSTRAP_SERVER));
+    network_config.bootstrap_service = Some(url2::url2!("{}", kangaroo_config.bootstrap_server));
     network_config.transport_pool.push(TransportConfig::WebRTC {
-        signal_url: SIGNALING_SERVER.into(),
+        signal_url: kangaroo_config.signaling_server.clone(),
     });
 
     config.network = network_config;
 
-    // TODO more graceful error handling
-    let config_string =
-        serde_yaml::to_string(&config).expect("Could not convert conductor config to string");
-
-    let conductor_config_path = fs.conductor_dir().join("conductor-config.yaml");
+    apply_conductor_config_overrides(fs, config)
+}
 
-    std::fs::write(conductor_config_path.clone(), config_string)
-        .expect("Could not write conductor config");
+/// Returns the conductor config with the admin interface at the given port. The overrides can't
+/// touch the admin interfaces, so setting them after the merge leaves the overrides intact.
+fn with_admin_interface(
+    config: &ConductorConfig,
+    admin_port: u16,
+    allowed_origins: AllowedOrigins,
+) -> ConductorConfig {
+    let mut config = config.clone();
+    config.admin_interfaces = Some(vec![AdminInterfaceConfig {
+        driver: InterfaceDriver::Websocket {
+            port: admin_port,
+            allowed_origins,
+        },
+    }]);
+    config
+}
 
-    let command =
-        Command::new_sidecar(format!("holochain-v{}", HOLOCHAIN_VERSION)).map_err(|err| {
-            AppError::LaunchHolochainError(LaunchHolochainError::SidecarBinaryCommandError(
-                format!("{}", err),
-            ))
-        })?;
+/// Writes the conductor config to the conductor dir and returns its path
+fn write_conductor_config(fs: &AppFileSystem, config: &ConductorConfig) -> AppResult<PathBuf> {
+    let config_string = serde_yaml::to_string(config)
+        .map_err(|e| LaunchHolochainError::FailedToOverwriteConfig(e.to_string()))?;
 
-    launch_holochain_process(log_level, command, conductor_config_path, password).await?;
+    let conductor_config_path = fs.conductor_dir().join("conductor-config.yaml");
+    std::fs::write(&conductor_config_path, config_string)?;
 
-    std::thread::sleep(Duration::from_millis(100));
+    Ok(conductor_config_path)
+}
 
-    // Try to connect twice. This fixes the os(111) error for now that occurs when the conducor is not ready yet.
-    let mut admin_ws = connect_to_admin_ws_with_retries(admin_port, 3).await?;
+fn pick_unused_port() -> AppResult<u16> {
+    portpicker::pick_unused_port().ok_or(AppError::LaunchHolochainError(
+        LaunchHolochainError::NoFreePort,
+    ))
+}
 
-    let app_port = {
-        let app_interfaces = admin_ws.list_app_interfaces().await.map_err(|e| {
-            LaunchHolochainError::CouldNotConnectToConductor(format!(
-                "Could not list app interfaces: {:?}",
-                e
-            ))
-        })?;
+/// Attaches an app interface at the configured app port, or at a free port. A free port may
+/// get taken before the conductor binds it, in which case another one is tried.
+async fn attach_app_interface(
+    admin_ws: &AdminWebsocket,
+    kangaroo_config: &KangarooConfig,
+    allowed_origins: AllowedOrigins,
+) -> AppResult<u16> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let port = match kangaroo_config.app_port {
+            Some(port) => port,
+            None => pick_unused_port()?,
+        };
+
+        match admin_ws
+            .attach_app_interface(
+                port,
+                allowed_origins.clone(),
+                Some(kangaroo_config.app_id.clone()),
+            )
+            .await
+        {
+            Ok(port) => return Ok(port),
+            Err(e) if kangaroo_config.app_port.is_none() && attempt < MAX_PORT_ATTEMPTS => {
+                log::warn!(
+                    "Failed to attach app interface at port {} ({}/{}): {:?}. Retrying with another port.",
+                    port,
+                    attempt,
+                    MAX_PORT_ATTEMPTS,
+                    e
+                );
+            }
+            Err(e) => {
+                return Err(LaunchHolochainError::CouldNotConnectToConductor(format!(
+                    "Could not attach app interface at port {}: {:?}",
+                    port, e
+                ))
+                .into())
+            }
+        }
+    }
+}
 
-        if app_interfaces.len() > 0 {
-            app_interfaces[0].port
-        } else {
-            let free_port = portpicker::pick_unused_port().expect("No ports free");
+/// Initializes the keystore if necessary, then starts it in the given mode and connects to it.
+/// Asks the passphrase provider for the passphrase again if it was incorrect.
+///
+/// Keystores of earlier versions were locked with the `LEGACY_PASSPHRASE`, so that one is tried
+/// before asking for the passphrase, until it turned out to be incorrect once.
+async fn launch_keystore(
+    fs: &AppFileSystem,
+    keystore_mode: KeystoreMode,
+    passphrase_provider: &dyn PassphraseProvider,
+    progress: &LaunchProgress,
+    log_level: log::Level,
+) -> AppResult<(MetaLairClient, Url2, String, bool)> {
+    // initialize lair keystore if necessary. The in-process keystore initializes itself.
+    let is_setup = !fs.keystore_initialized();
+    let mut setup_password = None;
+    if is_setup {
+        setup_password = Some(match keystore_mode {
+            KeystoreMode::Sidecar => {
+                initialize_keystore(fs.keystore_dir(), passphrase_provider, progress).await?
+            }
+            KeystoreMode::InProc => {
+                let password = passphrase_provider
+                    .passphrase(PassphraseMode::Setup)
+                    .await?;
+                // the in-process keystore gets initialized together with unlocking it
+                progress.start(LaunchStage::KeystoreInit);
+                password
+            }
+        });
+    } else {
+        progress.skip(LaunchStage::KeystoreInit);
+    }
+    let mut try_legacy_passphrase = !is_setup && !fs.read_profile_metadata().passphrase_chosen;
 
-            admin_ws
-                .attach_app_interface(free_port, AllowedOrigins::Any, Some(APP_ID.to_owned()))
+    loop {
+        let is_legacy_attempt = try_legacy_passphrase;
+        let password = match setup_password.take() {
+            Some(password) => password,
+            None if try_legacy_passphrase => {
+                try_legacy_passphrase = false;
+                log::info!("Trying the passphrase of earlier versions to unlock the keystore.");
+                String::from(LEGACY_PASSPHRASE)
+            }
+            None => {
+                passphrase_provider
+                    .passphrase(PassphraseMode::Unlock)
+                    .await?
+            }
+        };
+
+        progress.start(LaunchStage::KeystoreUnlock);
+
+        let result = match keystore_mode {
+            KeystoreMode::Sidecar => {
+                // spawn lair keystore process and connect to it
+                match launch_lair_keystore_process(
+                    log_level.clone(),
+                    fs.keystore_dir(),
+                    password.clone(),
+                )
                 .await
-                .or(Err(LaunchHolochainError::CouldNotConnectToConductor(
-                    "Could not attach app interface".into(),
-                )))?;
-            free_port
+                {
+                    Ok(lair_url) => holochain_keystore::lair_keystore::spawn_lair_keystore(
+                        lair_url.clone(),
+                        password.as_bytes().into(),
+                    )
+                    .await
+                    .map(|meta_lair_client| (meta_lair_client, lair_url))
+                    .map_err(|e| LairKeystoreError::SpawnMetaLairClientError(format!("{}", e))),
+                    Err(e) => Err(e),
+                }
+            }
+            KeystoreMode::InProc => {
+                spawn_in_proc_lair_keystore(fs.keystore_dir(), password.clone()).await
+            }
+        };
+
+        match result {
+            Ok((meta_lair_client, lair_url)) => {
+                if keystore_mode == KeystoreMode::InProc && is_setup {
+                    progress.finish(LaunchStage::KeystoreInit);
+                }
+                if is_setup {
+                    fs.update_profile_metadata(|metadata| metadata.passphrase_chosen = true)?;
+                }
+                progress.finish(LaunchStage::KeystoreUnlock);
+                return Ok((meta_lair_client, lair_url, password, is_legacy_attempt));
+            }
+            Err(LairKeystoreError::IncorrectPassword) if is_legacy_attempt => {
+                log::info!("The keystore is not locked with the passphrase of earlier versions.");
+                fs.update_profile_metadata(|metadata| metadata.passphrase_chosen = true)?;
+            }
+            Err(LairKeystoreError::IncorrectPassword) => {
+                log::warn!("Incorrect passphrase for the lair keystore.");
+                if !passphrase_provider.on_incorrect_passphrase() {
+                    return Err(LairKeystoreError::IncorrectPassword.into());
+                }
+            }
+            Err(e) => return Err(e.into()),
         }
-    };
+    }
+}
 
-    let network_seed = match fs.read_profile_network_seed() {
-        Some(seed) => Some(seed),
-        None => DEFAULT_NETWORK_SEED.map(String::from),
-    };
+/// Finds the agent key the app was installed under before, to install it under the same one
+/// again. It is only reused if its private key is still in the keystore, otherwise the app could
+/// not sign anything under it.
+async fn previous_agent_key(
+    fs: &AppFileSystem,
+    meta_lair_client: &MetaLairClient,
+) -> AppResult<Option<AgentPubKey>> {
+    let metadata = fs.read_profile_metadata();
+
+    let entries = meta_lair_client
+        .lair_client()
+        .list_entries()
+        .await
+        .map_err(|e| LairKeystoreError::ListEntriesError(e.to_string()))?;
+    let keys_in_keystore = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            LairEntryInfo::Seed { seed_info, .. }
+            | LairEntryInfo::DeepLockedSeed { seed_info, .. } => Some(AgentPubKey::from_raw_32(
+                seed_info.ed25519_pub_key[..].to_vec(),
+            )),
+            _ => None,
+        })
+        .collect::<Vec<AgentPubKey>>();
+
+    match metadata.agent_pub_key {
+        Some(agent_pub_key) => {
+            let agent_pub_key: AgentPubKey = match AgentPubKeyB64::from_b64_str(&agent_pub_key) {
+                Ok(agent_pub_key) => agent_pub_key.into(),
+                Err(e) => {
+                    log::error!(
+                        "Invalid agent key {} in the profile metadata: {:?}",
+                        agent_pub_key,
+                        e
+                    );
+                    return Ok(None);
+                }
+            };
+            if !keys_in_keystore.contains(&agent_pub_key) {
+                log::warn!(
+                    "The agent key {} of the profile metadata is not in the keystore anymore",
+                    agent_pub_key
+                );
+                return Ok(None);
+            }
+            Ok(Some(agent_pub_key))
+        }
+        None if metadata.new_identity_requested => Ok(None),
+        // older versions of the app didn't record the agent key, e.g. for migrated profiles or
+        // profiles whose conductor data was reset before it got recorded, but the keystores they
+        // created hold no other key than the one the app was installed under. The keystore of a
+        // profile that never had the app installed is empty.
+        None => match keys_in_keystore.as_slice() {
+            [] => Ok(None),
+            [agent_pub_key] => {
+                log::info!("Found the agent key {} in the keystore", agent_pub_key);
+                record_agent_key_if_missing(fs, agent_pub_key)?;
+                Ok(Some(agent_pub_key.clone()))
+            }
+            keys => {
+                log::warn!(
+                    "The keystore holds {} keys, the one the app was installed under is unknown",
+                    keys.len()
+                );
+                Ok(None)
+            }
+        },
+    }
+}
 
-    install_app_if_necessary(network_seed, &mut admin_ws).await?;
+/// Records the agent key the app is installed under, for installs from before it was recorded
+fn record_agent_key_if_missing(fs: &AppFileSystem, agent_pub_key: &AgentPubKey) -> AppResult<()> {
+    if fs.read_profile_metadata().agent_pub_key.is_some() {
+        return Ok(());
+    }
+    fs.update_profile_metadata(|metadata| {
+        metadata.agent_pub_key = Some(AgentPubKeyB64::from(agent_pub_key.clone()).to_string());
+    })
+}
 
-    Ok((meta_lair_client, app_port, admin_port))
+/// Decodes the happ bundled with this app
+fn bundled_app_bundle() -> AppResult<AppBundle> {
+    // replace-me --- replace the path with the correct path to your .happ file here
+    let app_bundle = AppBundle::decode(include_bytes!("../../pouch/replace-me.happ"))
+        .map_err(|e| AppError::AppBundleError(e))?;
+    Ok(app_bundle)
 }
 
 pub async fn install_app_if_necessary(
+    fs: &AppFileSystem,
+    app_id: &String,
     network_seed: Option<String>,
+    membrane_proof_roles: &[String],
+    membrane_proof_provider: &dyn MembraneProofProvider,
+    meta_lair_client: &MetaLairClient,
     admin_ws: &mut AdminWebsocket,
+    progress: &LaunchProgress,
 ) -> AppResult<()> {
     let apps = admin_ws
         .list_apps(None)
         .await
         .map_err(|e| AppError::ConductorApiError(e))?;
 
-    if !apps
-        .iter()
-        .map(|info| info.installed_app_id.clone())
-        .collect::<Vec<String>>()
-        .contains(&APP_ID.to_string())
-    {
-        let agent_key = admin_ws
-            .generate_agent_pub_key()
-            .await
-            .map_err(|e| AppError::ConductorApiError(e))?;
+    if let Some(app_info) = apps.iter().find(|info| &info.installed_app_id == app_id) {
+        record_agent_key_if_missing(fs, &app_info.agent_pub_key)?;
 
-        // replace-me --- replace the path with the correct path to your .happ file here
-        let app_bundle = AppBundle::decode(include_bytes!("../../pouch/replace-me.happ"))
-            .map_err(|e| AppError::AppBundleError(e))?;
+        progress.skip(LaunchStage::AppInstall);
+        progress.skip(LaunchStage::AppEnable);
 
-        admin_ws
-            .install_app(InstallAppPayload {
-                source: holochain_types::prelude::AppBundleSource::Bundle(app_bundle),
-                agent_key: agent_key.clone(),
-                network_seed: network_seed.clone(),
-                installed_app_id: Some(APP_ID.to_string()),
-                membrane_proofs: HashMap::new(),
-            })
-            .await
-            .map_err(|e| AppError::ConductorApiError(e))?;
+        update_coordinators_if_necessary(fs, app_id, &bundled_app_bundle()?, admin_ws, progress)
+            .await?;
+    } else {
+        progress.start(LaunchStage::AppInstall);
+
+        // a new agent key is only generated on the very first install, or after the user
+        // asked for a new identity, so that a reinstall keeps the user's identity
+        let agent_key = match previous_agent_key(fs, meta_lair_client).await? {
+            Some(agent_key) => {
+                log::info!(
+                    "Installing the app under its previous agent key {}",
+                    agent_key
+                );
+                agent_key
+            }
+            None => admin_ws
+                .generate_agent_pub_key()
+                .await
+                .map_err(|e| AppError::ConductorApiError(e))?,
+        };
+
+        let app_bundle = bundled_app_bundle()?;
+
+        // the integrity zomes check the membrane proofs at genesis, so a rejected proof
+        // makes the installation fail and can be retried with a different one
+        let mut rejection = None;
+        loop {
+            let membrane_proofs = match membrane_proof_roles.is_empty() {
+                true => HashMap::new(),
+                false => membrane_proof_provider
+                    .membrane_proofs(membrane_proof_roles, rejection.take())
+                    .await?
+                    .into_iter()
+                    .map(|(role, proof)| (role, to_membrane_proof(proof)))
+                    .collect(),
+            };
+
+            match admin_ws
+                .install_app(InstallAppPayload {
+                    source: holochain_types::prelude::AppBundleSource::Bundle(app_bundle.clone()),
+                    agent_key: agent_key.clone(),
+                    network_seed: network_seed.clone(),
+                    installed_app_id: Some(app_id.clone()),
+                    membrane_proofs,
+                })
+                .await
+            {
+                Ok(_) => break,
+                Err(e) if !membrane_proof_roles.is_empty() && is_genesis_failure(&e) => {
+                    log::warn!("Genesis failed with the provided membrane proofs: {:?}", e);
+                    if !membrane_proof_provider.can_retry() {
+                        return Err(AppError::MembraneProofError(format!(
+                            "Genesis failed with the provided membrane proofs: {:?}",
+                            e
+                        )));
+                    }
+                    rejection = Some(format!("The membrane proofs were rejected: {:?}", e));
+                }
+                Err(e) => return Err(AppError::ConductorApiError(e)),
+            }
+        }
+
+        fs.update_profile_metadata(|metadata| {
+            metadata.agent_pub_key = Some(AgentPubKeyB64::from(agent_key).to_string());
+            metadata.new_identity_requested = false;
+        })?;
+
+        progress.finish(LaunchStage::AppInstall);
+        progress.start(LaunchStage::AppEnable);
 
         admin_ws
-            .enable_app(APP_ID.to_string())
+            .enable_app(app_id.clone())
             .await
             .map_err(|e| AppError::ConductorApiError(e))?;
+
+        progress.finish(LaunchStage::AppEnable);
+        // a fresh install comes with the bundled coordinators already
+        progress.skip(LaunchStage::CoordinatorUpdate);
     }
 
     Ok(())
 }
-
-async fn connect_to_admin_ws_with_retries(
-    admin_port: u16,
-    max_retries: u32,
-) -> Result<AdminWebsocket, LaunchHolochainError> {
-    let mut attempts = 0;
-
-    loop {
-        match AdminWebsocket::connect(SocketAddr::from(([127, 0, 0, 1], admin_port))).await {
-            Ok(ws) => return Ok(ws),
-            Err(_) if attempts < max_retries => {
-                attempts += 1;
-                log::error!("[HOLOCHAIN] Could not connect to the AdminWebsocket. Attempt {}/{}. Retrying in 5 seconds.", attempts, max_retries);
-                std::thread::sleep(Duration::from_secs(5));
-            }
-            Err(err) => {
-                return Err(LaunchHolochainError::CouldNotConnectToConductor(format!(
-                    "{}",
-                    err
-                )));
-            }
-        }
-    }
-}
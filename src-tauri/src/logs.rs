@@ -8,10 +8,10 @@ use log4rs::{
 };
 
 use crate::app_state::filesystem::AppFileSystem;
-use crate::config::APP_ID;
 
-pub fn setup_logs(fs: AppFileSystem) -> Result<(), String> {
-    let logs_path = fs.profile_log_dir.join(format!("{}.log", APP_ID));
+/// Logs to a file named after the resolved app id in the log directory of the profile
+pub fn setup_logs(fs: AppFileSystem, app_id: &str) -> Result<(), String> {
+    let logs_path = fs.profile_log_dir.join(format!("{}.log", app_id));
 
     let logfile = FileAppender::builder()
         .encoder(Box::new(PatternEncoder::new("[{d}] {l} - {m}\n")))
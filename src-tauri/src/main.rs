@@ -7,24 +7,44 @@ use app::{
     window::handle_menu_event,
 };
 use commands::{
+    admin::app_info,
+    app_token::refresh_app_token,
+    clone_cell::{create_clone_cell, delete_clone_cell, disable_clone_cell, enable_clone_cell},
+    launch_error::{open_logs, retry_launch},
     log::log,
+    membrane_proofs::submit_membrane_proofs,
+    migration::{decline_migration, migrate_profiles},
+    passphrase::{change_passphrase, open_change_passphrase, submit_passphrase},
     profile::{
-        get_active_profile, get_existing_profiles, open_profile_settings, set_active_profile,
+        get_active_profile, get_existing_profiles, get_profiles_migrated_from,
+        open_profile_settings, reset_profile_conductor, reset_profile_identity, set_active_profile,
         set_profile_network_seed,
     },
+    progress::get_launch_progress,
     restart::restart,
     sign_zome_call::sign_zome_call,
+    storage::{
+        archive_storage, delete_archive, delete_storage, get_storage_usage, open_storage_manager,
+    },
 };
 use tauri::{RunEvent, SystemTray};
 
 mod app;
 mod app_state;
 mod commands;
+mod conductor_config;
 mod config;
+mod coordinators;
 mod errors;
 mod launch;
 mod logs;
+mod membrane_proofs;
+mod migration;
+mod origins;
+mod passphrase;
 mod process;
+mod rekey;
+mod storage;
 mod utils;
 
 fn main() {
@@ -39,9 +59,32 @@ fn main() {
             set_active_profile,
             get_active_profile,
             get_existing_profiles,
+            get_profiles_migrated_from,
             set_profile_network_seed,
             open_profile_settings,
             restart,
+            submit_passphrase,
+            submit_membrane_proofs,
+            migrate_profiles,
+            decline_migration,
+            get_storage_usage,
+            archive_storage,
+            delete_storage,
+            delete_archive,
+            open_storage_manager,
+            change_passphrase,
+            open_change_passphrase,
+            get_launch_progress,
+            retry_launch,
+            open_logs,
+            reset_profile_conductor,
+            reset_profile_identity,
+            app_info,
+            create_clone_cell,
+            enable_clone_cell,
+            disable_clone_cell,
+            delete_clone_cell,
+            refresh_app_token,
         ])
         .setup(setup_app)
         .build(tauri::generate_context!());
@@ -50,9 +93,9 @@ fn main() {
         Ok(builder) => {
             builder.run(|_app_handle, event| {
                 // This event is emitted upon quitting the App via cmq+Q on macOS.
-                // Sidecar binaries need to get explicitly killed in this case (https://github.com/holochain/launcher/issues/141)
+                // Sidecar binaries need to get explicitly stopped in this case (https://github.com/holochain/launcher/issues/141)
                 if let RunEvent::Exit = event {
-                    tauri::api::process::kill_children();
+                    process::shutdown::shutdown();
                 }
 
                 // optional (systray):
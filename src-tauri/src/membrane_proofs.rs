@@ -0,0 +1,244 @@
+use std::{collections::HashMap, path::PathBuf, sync::OnceLock};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver},
+    future::BoxFuture,
+    lock::Mutex,
+    FutureExt, StreamExt,
+};
+use holochain_client::ConductorApiError;
+use holochain_types::prelude::{MembraneProof, SerializedBytes, UnsafeBytes};
+use serde_json::Value;
+use tauri::{App, AppHandle, Manager};
+
+use crate::{
+    app::membrane_proofs::{
+        build_membrane_proofs_window, report_membrane_proof_error, MembraneProofsSender,
+    },
+    app_state::filesystem::AppFileSystem,
+    errors::{AppError, AppResult},
+};
+
+/// Name of the file in the profile config directory that may hold the membrane proofs,
+/// as a JSON object of base64 encoded proofs by role name
+pub const MEMBRANE_PROOFS_FILE_NAME: &str = "membrane-proofs.json";
+
+/// Membrane proofs by role name
+pub type MembraneProofs = HashMap<String, Vec<u8>>;
+
+/// Source of the membrane proofs passed to the conductor when the app is installed
+pub trait MembraneProofProvider: Send + Sync {
+    /// Returns the membrane proofs for the given roles. `rejection` is the reason why the
+    /// proofs returned before were rejected, if any.
+    fn membrane_proofs<'a>(
+        &'a self,
+        roles: &'a [String],
+        rejection: Option<String>,
+    ) -> BoxFuture<'a, AppResult<MembraneProofs>>;
+
+    /// Whether the provider can come up with different proofs when asked again
+    fn can_retry(&self) -> bool {
+        false
+    }
+}
+
+/// Returns the provider of the membrane proofs. The proofs are only read once an install
+/// asks for them, so leftover proofs can't get in the way of launching an installed app.
+pub fn membrane_proof_provider(
+    app: &App,
+    fs: &AppFileSystem,
+) -> AppResult<Box<dyn MembraneProofProvider>> {
+    let cli_matches = app.get_cli_matches()?;
+    let cli_args = match cli_matches
+        .args
+        .get("membrane-proof")
+        .map(|data| data.value.clone())
+    {
+        Some(Value::Array(args)) => Some(
+            args.iter()
+                .map(|arg| arg.as_str().unwrap_or_default().to_string())
+                .collect(),
+        ),
+        _ => None,
+    };
+
+    Ok(Box::new(LazyMembraneProofProvider {
+        app_handle: app.handle(),
+        cli_args,
+        proofs_path: fs.profile_config_dir.join(MEMBRANE_PROOFS_FILE_NAME),
+        provider: OnceLock::new(),
+    }))
+}
+
+pub fn decode_membrane_proof(role: &str, proof: &str) -> AppResult<Vec<u8>> {
+    STANDARD.decode(proof.trim()).map_err(|e| {
+        AppError::MembraneProofError(format!(
+            "The membrane proof for role {} is not valid base64: {}",
+            role, e
+        ))
+    })
+}
+
+pub fn to_membrane_proof(proof: Vec<u8>) -> MembraneProof {
+    MembraneProof::new(SerializedBytes::from(UnsafeBytes::from(proof)))
+}
+
+/// Whether the installation of an app failed because the genesis of one of its cells failed,
+/// which is how the integrity zomes reject a membrane proof
+pub fn is_genesis_failure(error: &ConductorApiError) -> bool {
+    let error = format!("{:?}", error);
+    error.contains("GenesisFailed") || error.contains("Genesis failed")
+}
+
+/// Picks the source of the membrane proofs the first time they are asked for: the
+/// `--membrane-proof role=base64` CLI arguments first, then the membrane proofs file of the
+/// profile, and asking the user in a window last
+pub struct LazyMembraneProofProvider {
+    app_handle: AppHandle,
+    cli_args: Option<Vec<String>>,
+    proofs_path: PathBuf,
+    provider: OnceLock<Box<dyn MembraneProofProvider>>,
+}
+
+impl LazyMembraneProofProvider {
+    fn build(&self) -> AppResult<Box<dyn MembraneProofProvider>> {
+        if let Some(args) = &self.cli_args {
+            let mut proofs = HashMap::new();
+            for arg in args {
+                let (role, proof) =
+                    arg.split_once('=')
+                        .ok_or(AppError::MembraneProofError(format!(
+                            "Expected --membrane-proof role=base64, got {:?}",
+                            arg
+                        )))?;
+                proofs.insert(role.to_string(), decode_membrane_proof(role, proof)?);
+            }
+            return Ok(Box::new(FixedMembraneProofProvider {
+                source: String::from("the command line"),
+                proofs,
+            }));
+        }
+
+        if self.proofs_path.exists() {
+            let content = std::fs::read_to_string(&self.proofs_path)?;
+            let encoded: HashMap<String, String> = serde_json::from_str(&content).map_err(|e| {
+                AppError::MembraneProofError(format!(
+                    "Failed to parse {:?}: {}",
+                    self.proofs_path, e
+                ))
+            })?;
+            let proofs = encoded
+                .iter()
+                .map(|(role, proof)| Ok((role.clone(), decode_membrane_proof(role, proof)?)))
+                .collect::<AppResult<MembraneProofs>>()?;
+            return Ok(Box::new(FixedMembraneProofProvider {
+                source: format!("{:?}", self.proofs_path),
+                proofs,
+            }));
+        }
+
+        Ok(Box::new(PromptMembraneProofProvider::new(
+            self.app_handle.clone(),
+        )))
+    }
+
+    fn provider(&self) -> AppResult<&dyn MembraneProofProvider> {
+        let provider = match self.provider.get() {
+            Some(provider) => provider,
+            None => {
+                let provider = self.build()?;
+                self.provider.get_or_init(|| provider)
+            }
+        };
+        Ok(provider.as_ref())
+    }
+}
+
+impl MembraneProofProvider for LazyMembraneProofProvider {
+    fn membrane_proofs<'a>(
+        &'a self,
+        roles: &'a [String],
+        rejection: Option<String>,
+    ) -> BoxFuture<'a, AppResult<MembraneProofs>> {
+        match self.provider() {
+            Ok(provider) => provider.membrane_proofs(roles, rejection),
+            Err(e) => futures::future::ready(Err(e)).boxed(),
+        }
+    }
+
+    fn can_retry(&self) -> bool {
+        self.provider
+            .get()
+            .map(|provider| provider.can_retry())
+            .unwrap_or(false)
+    }
+}
+
+/// Returns the membrane proofs passed on the command line or in a file
+pub struct FixedMembraneProofProvider {
+    source: String,
+    proofs: MembraneProofs,
+}
+
+impl MembraneProofProvider for FixedMembraneProofProvider {
+    fn membrane_proofs<'a>(
+        &'a self,
+        roles: &'a [String],
+        _rejection: Option<String>,
+    ) -> BoxFuture<'a, AppResult<MembraneProofs>> {
+        let result = match roles.iter().find(|role| !self.proofs.contains_key(*role)) {
+            Some(role) => Err(AppError::MembraneProofError(format!(
+                "No membrane proof for role {} in {}",
+                role, self.source
+            ))),
+            None => Ok(self.proofs.clone()),
+        };
+        futures::future::ready(result).boxed()
+    }
+}
+
+/// Asks the user for the membrane proofs in the membrane proofs window
+pub struct PromptMembraneProofProvider {
+    app_handle: AppHandle,
+    proofs_rx: Mutex<UnboundedReceiver<MembraneProofs>>,
+}
+
+impl PromptMembraneProofProvider {
+    pub fn new(app_handle: AppHandle) -> Self {
+        let (proofs_tx, proofs_rx) = mpsc::unbounded();
+        app_handle.manage(MembraneProofsSender(proofs_tx));
+        PromptMembraneProofProvider {
+            app_handle,
+            proofs_rx: Mutex::new(proofs_rx),
+        }
+    }
+}
+
+impl MembraneProofProvider for PromptMembraneProofProvider {
+    fn membrane_proofs<'a>(
+        &'a self,
+        roles: &'a [String],
+        rejection: Option<String>,
+    ) -> BoxFuture<'a, AppResult<MembraneProofs>> {
+        async move {
+            build_membrane_proofs_window(&self.app_handle, roles)?;
+            if let Some(rejection) = rejection {
+                report_membrane_proof_error(&self.app_handle, rejection);
+            }
+            self.proofs_rx
+                .lock()
+                .await
+                .next()
+                .await
+                .ok_or(AppError::MembraneProofError(String::from(
+                    "The membrane proofs window was closed",
+                )))
+        }
+        .boxed()
+    }
+
+    fn can_retry(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,220 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{
+    app_state::{
+        filesystem::{AppFileSystem, Profile},
+        metadata::{update_profile_metadata, PROFILE_METADATA_FILE_NAME},
+    },
+    errors::{AppError, AppResult},
+    utils::{copy_dir_all, dir_name, version_of_dir},
+};
+
+/// Marks the data directory of this version as having dealt with the data of older versions,
+/// so that the migration is only offered once
+const MIGRATION_MARKER_FILE_NAME: &str = ".migrationDone";
+
+const LAIR_CONFIG_FILE_NAME: &str = "lair-keystore-config.yaml";
+
+/// Files of a profile data directory that are carried forward next to the keystore
+const PROFILE_FILES: &[&str] = &[".networkSeed", PROFILE_METADATA_FILE_NAME];
+
+/// Data directory of an older version of the app whose profiles can be carried forward
+#[derive(Serialize, Debug, Clone)]
+pub struct MigrationCandidate {
+    /// Breaking version the data directory belongs to, e.g. `0.2.x`
+    pub from_version: String,
+    pub profiles: Vec<ProfileMigration>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProfileMigration {
+    pub profile: Profile,
+    /// Why the profile can't be migrated, if it can't
+    pub problem: Option<String>,
+}
+
+/// Looks for the newest data directory of an older breaking version with profiles in it.
+/// Only offered while no profile exists for this version yet and the migration hasn't been
+/// done or declined before.
+pub fn find_migration_candidate(fs: &AppFileSystem) -> AppResult<Option<MigrationCandidate>> {
+    if fs.app_data_dir.join(MIGRATION_MARKER_FILE_NAME).exists()
+        || fs
+            .get_existing_profiles()
+            .map(|profiles| !profiles.is_empty())
+            .unwrap_or(false)
+    {
+        return Ok(None);
+    }
+
+    let current_version = match version_of_dir(&fs.app_data_dir) {
+        Some(version) => version,
+        None => return Ok(None),
+    };
+    let versions_dir = match fs.app_data_dir.parent() {
+        Some(dir) if dir.exists() => dir,
+        _ => return Ok(None),
+    };
+
+    let mut older_versions = std::fs::read_dir(versions_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| version_of_dir(&entry.path()).map(|version| (version, entry.path())))
+        .filter(|(version, _)| version < &current_version)
+        .collect::<Vec<(semver::Version, PathBuf)>>();
+    older_versions.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    for (_, version_dir) in older_versions {
+        let profiles = profile_dirs(&version_dir)?
+            .into_iter()
+            .map(|profile| ProfileMigration {
+                problem: migration_problem(fs, &version_dir, &profile),
+                profile,
+            })
+            .collect::<Vec<ProfileMigration>>();
+        if !profiles.is_empty() {
+            return Ok(Some(MigrationCandidate {
+                from_version: dir_name(&version_dir),
+                profiles,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Carries the profiles of the offered older version forward: their keystores with the agent
+/// keys, network seeds, metadata and per-profile config. The conductor databases are left
+/// behind, since their format may have changed between the versions. Profiles that can't be
+/// migrated are reported with the reason instead.
+pub fn migrate_profiles(
+    fs: &AppFileSystem,
+    from_version: &str,
+) -> AppResult<Vec<ProfileMigration>> {
+    let candidate = find_migration_candidate(fs)?
+        .filter(|candidate| candidate.from_version == from_version)
+        .ok_or(AppError::FileSystemError(format!(
+            "There is no data of version {} to migrate from",
+            from_version
+        )))?;
+    let old_app_data_dir = fs.app_data_dir.with_file_name(from_version);
+
+    let mut results = Vec::new();
+    for migration in candidate.profiles {
+        let problem = match migration.problem {
+            Some(problem) => Some(problem),
+            None => migrate_profile(fs, &old_app_data_dir, from_version, &migration.profile)
+                .err()
+                .map(|e| {
+                    log::error!("Failed to migrate profile {}: {}", migration.profile, e);
+                    // don't leave a half migrated profile behind
+                    let _ = std::fs::remove_dir_all(fs.app_data_dir.join(&migration.profile));
+                    format!("Migration failed: {}", e)
+                }),
+        };
+        results.push(ProfileMigration {
+            profile: migration.profile,
+            problem,
+        });
+    }
+
+    let active_profile_path = old_app_data_dir.join(".activeProfile");
+    if active_profile_path.exists() {
+        std::fs::copy(active_profile_path, fs.app_data_dir.join(".activeProfile"))?;
+    }
+    mark_migration_done(fs, from_version)?;
+
+    Ok(results)
+}
+
+/// Remembers that the user doesn't want to migrate the data of older versions
+pub fn decline_migration(fs: &AppFileSystem) -> AppResult<()> {
+    mark_migration_done(fs, "declined")
+}
+
+fn mark_migration_done(fs: &AppFileSystem, content: &str) -> AppResult<()> {
+    std::fs::create_dir_all(&fs.app_data_dir)?;
+    std::fs::write(fs.app_data_dir.join(MIGRATION_MARKER_FILE_NAME), content)?;
+    Ok(())
+}
+
+fn migration_problem(
+    fs: &AppFileSystem,
+    old_app_data_dir: &Path,
+    profile: &Profile,
+) -> Option<String> {
+    if !old_app_data_dir
+        .join(profile)
+        .join("keystore")
+        .join(LAIR_CONFIG_FILE_NAME)
+        .exists()
+    {
+        return Some(String::from(
+            "The profile has no keystore, there are no keys to carry forward.",
+        ));
+    }
+    if fs.app_data_dir.join(profile).exists() {
+        return Some(String::from(
+            "A profile with this name exists already in this version.",
+        ));
+    }
+    None
+}
+
+fn migrate_profile(
+    fs: &AppFileSystem,
+    old_app_data_dir: &Path,
+    from_version: &str,
+    profile: &Profile,
+) -> AppResult<()> {
+    log::info!(
+        "Migrating profile {} from version {}",
+        profile,
+        from_version
+    );
+    let old_profile_dir = old_app_data_dir.join(profile);
+    let new_profile_dir = fs.app_data_dir.join(profile);
+
+    let old_keystore_dir = old_profile_dir.join("keystore");
+    let new_keystore_dir = new_profile_dir.join("keystore");
+    copy_dir_all(&old_keystore_dir, &new_keystore_dir)?;
+
+    // the lair config refers to the files of the keystore by absolute paths
+    let lair_config_path = new_keystore_dir.join(LAIR_CONFIG_FILE_NAME);
+    let lair_config = std::fs::read_to_string(&lair_config_path)?.replace(
+        old_keystore_dir.to_string_lossy().as_ref(),
+        new_keystore_dir.to_string_lossy().as_ref(),
+    );
+    std::fs::write(lair_config_path, lair_config)?;
+
+    for file in PROFILE_FILES {
+        if old_profile_dir.join(file).exists() {
+            std::fs::copy(old_profile_dir.join(file), new_profile_dir.join(file))?;
+        }
+    }
+
+    let old_profile_config_dir = fs.app_config_dir.join(from_version).join(profile);
+    if old_profile_config_dir.exists() {
+        let new_profile_config_dir = fs
+            .profile_config_dir
+            .parent()
+            .map(|versions_dir| versions_dir.join(profile))
+            .ok_or(AppError::FileSystemError(String::from(
+                "Could not resolve the config dir of this version",
+            )))?;
+        copy_dir_all(&old_profile_config_dir, &new_profile_config_dir)?;
+    }
+
+    update_profile_metadata(&new_profile_dir, |metadata| {
+        metadata.migrated_from = Some(old_profile_dir.to_string_lossy().to_string());
+    })
+}
+
+fn profile_dirs(app_data_dir: &Path) -> AppResult<Vec<Profile>> {
+    Ok(std::fs::read_dir(app_data_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect())
+}
@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+
+use holochain_types::websocket::AllowedOrigins;
+use tauri::{
+    utils::config::{AppUrl, WindowUrl},
+    AppHandle,
+};
+
+use crate::config::KangarooConfig;
+
+/// Origin sent by the holochain client that the app itself uses to talk to the conductor.
+/// Browsers don't let web pages set the Origin header, so allowing it does not open up the interfaces
+/// to other local web pages.
+const HOLOCHAIN_CLIENT_ORIGIN: &str = "holochain_websocket";
+
+/// Value of `dev_allowed_origins` that allows any origin
+const ANY_ORIGIN: &str = "*";
+
+/// Origins allowed to connect to the admin and app interfaces of the conductor: the origin of
+/// the Tauri webview on this platform, the holochain client of the app itself and the
+/// `dev_allowed_origins` from the config.
+pub fn allowed_origins(app_handle: &AppHandle, kangaroo_config: &KangarooConfig) -> AllowedOrigins {
+    origins_with(
+        webview_origin(app_handle),
+        &kangaroo_config.dev_allowed_origins,
+    )
+}
+
+fn origins_with(webview_origin: String, dev_allowed_origins: &[String]) -> AllowedOrigins {
+    if dev_allowed_origins
+        .iter()
+        .any(|origin| origin == ANY_ORIGIN)
+    {
+        log::warn!("Allowing any origin to connect to the conductor.");
+        return AllowedOrigins::Any;
+    }
+
+    let mut origins = HashSet::new();
+    origins.insert(webview_origin);
+    origins.insert(String::from(HOLOCHAIN_CLIENT_ORIGIN));
+    origins.extend(dev_allowed_origins.iter().cloned());
+
+    log::info!("Origins allowed to connect to the conductor: {:?}", origins);
+
+    AllowedOrigins::Origins(origins)
+}
+
+/// The origin the webview loads the UI from, as determined by Tauri
+fn webview_origin(app_handle: &AppHandle) -> String {
+    let config = app_handle.config();
+    // the UI is served from the devPath when running `tauri dev`
+    let base_path = match cfg!(debug_assertions) {
+        true => &config.build.dev_path,
+        false => &config.build.dist_dir,
+    };
+
+    match base_path {
+        AppUrl::Url(WindowUrl::External(url)) => url.origin().ascii_serialization(),
+        _ => platform_origin(&config.tauri.security.dangerous_use_http_scheme),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn platform_origin(use_http_scheme: &bool) -> String {
+    match use_http_scheme {
+        true => String::from("http://tauri.localhost"),
+        false => String::from("https://tauri.localhost"),
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn platform_origin(_use_http_scheme: &bool) -> String {
+    String::from("tauri://localhost")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc};
+
+    use holochain::conductor::{
+        config::{ConductorConfig, KeystoreConfig},
+        Conductor,
+    };
+    use holochain_websocket::{connect, ConnectRequest, WebsocketConfig};
+
+    use super::*;
+    use crate::utils::vec_to_locked;
+
+    async fn connect_with_origin(port: u16, origin: &str) -> std::io::Result<()> {
+        let request = ConnectRequest::new(SocketAddr::from(([127, 0, 0, 1], port)))
+            .try_set_header("Origin", origin)?;
+        connect(Arc::new(WebsocketConfig::CLIENT_DEFAULT), request)
+            .await
+            .map(|_| ())
+    }
+
+    /// Attaches an app interface to a real conductor with the origins the app allows and
+    /// connects to it like a web page of another origin would
+    #[tokio::test(flavor = "multi_thread")]
+    async fn foreign_origin_is_rejected() {
+        let tmp = tempfile::tempdir().unwrap();
+        let conductor = Conductor::builder()
+            .config(ConductorConfig {
+                data_root_path: Some(tmp.path().to_path_buf().into()),
+                keystore: KeystoreConfig::DangerTestKeystore,
+                ..Default::default()
+            })
+            .passphrase(Some(vec_to_locked(b"passphrase".to_vec()).unwrap()))
+            .build()
+            .await
+            .unwrap();
+
+        let allowed_origins = origins_with(String::from("tauri://localhost"), &[]);
+        let port = conductor
+            .clone()
+            .add_app_interface(either::Either::Left(0), allowed_origins, None)
+            .await
+            .unwrap();
+
+        assert!(connect_with_origin(port, "http://localhost:8080")
+            .await
+            .is_err());
+        assert!(connect_with_origin(port, "tauri://localhost").await.is_ok());
+        assert!(connect_with_origin(port, HOLOCHAIN_CLIENT_ORIGIN)
+            .await
+            .is_ok());
+
+        conductor.shutdown().await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn wildcard_allows_any_origin() {
+        assert_eq!(
+            origins_with(String::from("tauri://localhost"), &[String::from("*")]),
+            AllowedOrigins::Any
+        );
+    }
+}
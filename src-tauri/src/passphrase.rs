@@ -0,0 +1,179 @@
+use std::path::PathBuf;
+
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver},
+    future::BoxFuture,
+    lock::Mutex,
+    FutureExt, StreamExt,
+};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    app::passphrase::{build_passphrase_window, report_passphrase_error, PassphraseSender},
+    errors::LairKeystoreError,
+};
+
+/// Passphrase that earlier versions of the Kangaroo hardcoded for every keystore
+pub const LEGACY_PASSPHRASE: &str = "pass";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassphraseMode {
+    /// No keystore exists yet, the passphrase is used to initialize it
+    Setup,
+    /// The keystore exists and needs to be unlocked with the existing passphrase
+    Unlock,
+}
+
+/// Source of the passphrase used to initialize and unlock the lair keystore
+/// and to encrypt the conductor databases
+pub trait PassphraseProvider: Send + Sync {
+    fn passphrase(&self, mode: PassphraseMode) -> BoxFuture<'_, Result<String, LairKeystoreError>>;
+
+    /// Gets called if the passphrase turned out to be incorrect. Returns whether
+    /// the provider can come up with a different passphrase when asked again.
+    fn on_incorrect_passphrase(&self) -> bool {
+        false
+    }
+}
+
+/// Which passphrase provider to use, set in the `[passphrase]` table of a `kangaroo.toml`
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "provider", rename_all = "snake_case", deny_unknown_fields)]
+pub enum PassphraseProviderConfig {
+    /// Asks the user in a window
+    #[default]
+    Prompt,
+    /// Reads the passphrase from an environment variable
+    Env { var: String },
+    /// Reads the passphrase from a file, ignoring a trailing newline
+    File { path: PathBuf },
+    /// Uses a fixed passphrase. Only meant for throwaway profiles.
+    Constant { value: String },
+}
+
+// Implemented by hand to not leak constant passphrases into the logs
+impl std::fmt::Debug for PassphraseProviderConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PassphraseProviderConfig::Prompt => write!(f, "Prompt"),
+            PassphraseProviderConfig::Env { var } => write!(f, "Env {{ var: {:?} }}", var),
+            PassphraseProviderConfig::File { path } => write!(f, "File {{ path: {:?} }}", path),
+            PassphraseProviderConfig::Constant { .. } => write!(f, "Constant {{ .. }}"),
+        }
+    }
+}
+
+impl PassphraseProviderConfig {
+    pub fn build(&self, app_handle: &AppHandle) -> Box<dyn PassphraseProvider> {
+        match self {
+            PassphraseProviderConfig::Prompt => {
+                Box::new(PromptPassphraseProvider::new(app_handle.clone()))
+            }
+            PassphraseProviderConfig::Env { var } => {
+                Box::new(EnvPassphraseProvider { var: var.clone() })
+            }
+            PassphraseProviderConfig::File { path } => {
+                Box::new(FilePassphraseProvider { path: path.clone() })
+            }
+            PassphraseProviderConfig::Constant { value } => {
+                Box::new(ConstantPassphraseProvider(value.clone()))
+            }
+        }
+    }
+}
+
+/// Asks the user for the passphrase in the passphrase window
+pub struct PromptPassphraseProvider {
+    app_handle: AppHandle,
+    passphrase_rx: Mutex<UnboundedReceiver<String>>,
+}
+
+impl PromptPassphraseProvider {
+    pub fn new(app_handle: AppHandle) -> Self {
+        let (passphrase_tx, passphrase_rx) = mpsc::unbounded();
+        app_handle.manage(PassphraseSender(passphrase_tx));
+        PromptPassphraseProvider {
+            app_handle,
+            passphrase_rx: Mutex::new(passphrase_rx),
+        }
+    }
+}
+
+impl PassphraseProvider for PromptPassphraseProvider {
+    fn passphrase(&self, mode: PassphraseMode) -> BoxFuture<'_, Result<String, LairKeystoreError>> {
+        async move {
+            build_passphrase_window(&self.app_handle, mode).map_err(|e| {
+                LairKeystoreError::PassphraseUnavailable(format!(
+                    "Failed to open the passphrase window: {}",
+                    e
+                ))
+            })?;
+            self.passphrase_rx.lock().await.next().await.ok_or(
+                LairKeystoreError::PassphraseUnavailable(String::from(
+                    "The passphrase window was closed",
+                )),
+            )
+        }
+        .boxed()
+    }
+
+    fn on_incorrect_passphrase(&self) -> bool {
+        report_passphrase_error(&self.app_handle, String::from("Incorrect passphrase."));
+        true
+    }
+}
+
+/// Reads the passphrase from an environment variable
+pub struct EnvPassphraseProvider {
+    pub var: String,
+}
+
+impl PassphraseProvider for EnvPassphraseProvider {
+    fn passphrase(
+        &self,
+        _mode: PassphraseMode,
+    ) -> BoxFuture<'_, Result<String, LairKeystoreError>> {
+        let result = std::env::var(&self.var).map_err(|e| {
+            LairKeystoreError::PassphraseUnavailable(format!(
+                "Failed to read environment variable {}: {}",
+                self.var, e
+            ))
+        });
+        futures::future::ready(result).boxed()
+    }
+}
+
+/// Reads the passphrase from a file
+pub struct FilePassphraseProvider {
+    pub path: PathBuf,
+}
+
+impl PassphraseProvider for FilePassphraseProvider {
+    fn passphrase(
+        &self,
+        _mode: PassphraseMode,
+    ) -> BoxFuture<'_, Result<String, LairKeystoreError>> {
+        let result = std::fs::read_to_string(&self.path)
+            .map(|passphrase| passphrase.trim_end_matches(&['\r', '\n']).to_string())
+            .map_err(|e| {
+                LairKeystoreError::PassphraseUnavailable(format!(
+                    "Failed to read passphrase file {:?}: {}",
+                    self.path, e
+                ))
+            });
+        futures::future::ready(result).boxed()
+    }
+}
+
+/// Always returns the same passphrase
+pub struct ConstantPassphraseProvider(pub String);
+
+impl PassphraseProvider for ConstantPassphraseProvider {
+    fn passphrase(
+        &self,
+        _mode: PassphraseMode,
+    ) -> BoxFuture<'_, Result<String, LairKeystoreError>> {
+        futures::future::ready(Ok(self.0.clone())).boxed()
+    }
+}
@@ -0,0 +1,326 @@
+use crate::errors::InitializeConductorError;
+
+/// Line printed by the conductor once it is up and running
+const CONDUCTOR_READY: &str = "Conductor ready.";
+
+/// Lines starting the report of a panic of the conductor
+const PANIC_START: &[&str] = &["FATAL PANIC PanicInfo", "Well, this is embarrassing"];
+
+/// Line ending the report of a panic of the conductor
+const PANIC_END: &str = "Thank you kindly!";
+
+/// Maximum number of lines of a panic report kept for the error message
+const MAX_PANIC_LINES: usize = 10;
+
+/// Part of the message the conductor panics with when it fails to start
+const INITIALIZATION_FAILED: &str = "Could not initialize Conductor from configuration";
+
+/// A known failure mode of the conductor, recognized by a line of its output
+struct ErrorPattern {
+    /// Substrings that must all be contained in the line
+    contains: &'static [&'static str],
+    /// Whether the line must be fatal, i.e. part of a panic report or of the message about the
+    /// conductor failing to initialize. The same errors are also logged as warnings while the
+    /// conductor recovers from them on its own.
+    fatal_only: bool,
+    error: fn(String) -> InitializeConductorError,
+}
+
+/// Known failure modes of the conductor, checked in order against every line of its output
+const ERROR_PATTERNS: &[ErrorPattern] = &[
+    #[cfg(unix)]
+    ErrorPattern {
+        contains: &["Address already in use"],
+        fatal_only: true,
+        error: InitializeConductorError::AddressAlreadyInUse,
+    },
+    // Windows reports the port being taken as the websocket connection being reset
+    #[cfg(windows)]
+    ErrorPattern {
+        contains: &["websocket_error_from_network=Io", "ConnectionReset"],
+        fatal_only: false,
+        error: InitializeConductorError::AddressAlreadyInUse,
+    },
+    ErrorPattern {
+        contains: &["AddrInUse"],
+        fatal_only: true,
+        error: InitializeConductorError::AddressAlreadyInUse,
+    },
+    ErrorPattern {
+        contains: &["code: NotADatabase", "file is not a database"],
+        fatal_only: true,
+        error: InitializeConductorError::SqliteError,
+    },
+    ErrorPattern {
+        contains: &["code: DatabaseCorrupt"],
+        fatal_only: true,
+        error: InitializeConductorError::SqliteError,
+    },
+    ErrorPattern {
+        contains: &["code: DatabaseBusy", "database is locked"],
+        fatal_only: true,
+        error: InitializeConductorError::DatabaseLocked,
+    },
+    ErrorPattern {
+        contains: &["code: DiskFull"],
+        fatal_only: true,
+        error: InitializeConductorError::DiskFull,
+    },
+    #[cfg(unix)]
+    ErrorPattern {
+        contains: &["No space left on device"],
+        fatal_only: true,
+        error: InitializeConductorError::DiskFull,
+    },
+    ErrorPattern {
+        contains: &["PermissionDenied"],
+        fatal_only: true,
+        error: InitializeConductorError::PermissionDenied,
+    },
+    ErrorPattern {
+        contains: &["KeystoreError"],
+        fatal_only: true,
+        error: InitializeConductorError::KeystoreError,
+    },
+    ErrorPattern {
+        contains: &["LairError"],
+        fatal_only: true,
+        error: InitializeConductorError::KeystoreError,
+    },
+    ErrorPattern {
+        contains: &["ConductorConfigError"],
+        fatal_only: true,
+        error: InitializeConductorError::InvalidConfig,
+    },
+];
+
+/// State of the launch of the conductor, as far as it can be told from its output
+#[derive(Debug, Clone)]
+pub enum ConductorLaunchState {
+    Pending,
+    Ready,
+    InitializeConductorError(InitializeConductorError),
+    /// The process exited without being ready or printing a known error
+    ExitedEarly {
+        code: Option<i32>,
+        signal: Option<i32>,
+    },
+}
+
+/// Classifies the output of the conductor while it is launching
+#[derive(Default)]
+pub struct ConductorOutputClassifier {
+    in_panic: bool,
+    panic_lines: Vec<String>,
+}
+
+impl ConductorOutputClassifier {
+    /// Classifies a line of stdout or stderr. Returns `Pending` as long as the outcome of the
+    /// launch is not known yet.
+    pub fn classify_line(&mut self, line: &str) -> ConductorLaunchState {
+        if line.contains(CONDUCTOR_READY) {
+            return ConductorLaunchState::Ready;
+        }
+
+        if PANIC_START.iter().any(|s| line.contains(s)) {
+            self.in_panic = true;
+        }
+        let fatal = self.in_panic || line.contains(INITIALIZATION_FAILED);
+
+        if let Some(pattern) = ERROR_PATTERNS.iter().find(|pattern| {
+            (fatal || !pattern.fatal_only) && pattern.contains.iter().all(|s| line.contains(s))
+        }) {
+            return ConductorLaunchState::InitializeConductorError((pattern.error)(
+                line.trim().to_string(),
+            ));
+        }
+
+        if self.in_panic {
+            if self.panic_lines.len() < MAX_PANIC_LINES {
+                self.panic_lines.push(line.trim().to_string());
+            }
+            // no known error was found within the panic report
+            if line.contains(PANIC_END) {
+                return ConductorLaunchState::InitializeConductorError(self.unknown_error());
+            }
+        }
+
+        ConductorLaunchState::Pending
+    }
+
+    /// Classifies the exit of the process before it was ready
+    pub fn classify_exit(&self, code: Option<i32>, signal: Option<i32>) -> ConductorLaunchState {
+        if self.in_panic {
+            return ConductorLaunchState::InitializeConductorError(self.unknown_error());
+        }
+        ConductorLaunchState::ExitedEarly { code, signal }
+    }
+
+    fn unknown_error(&self) -> InitializeConductorError {
+        InitializeConductorError::UnknownError(format!(
+            "Unknown error when trying to initialize conductor. See log file for details. {}",
+            self.panic_lines.join(" ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replays recorded conductor output line by line, like the launch does
+    fn replay(output: &str) -> ConductorLaunchState {
+        let mut classifier = ConductorOutputClassifier::default();
+        for line in output.lines() {
+            let state = classifier.classify_line(line);
+            if !matches!(state, ConductorLaunchState::Pending) {
+                return state;
+            }
+        }
+        classifier.classify_exit(Some(101), None)
+    }
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/fixtures/conductor_output/",
+                $name,
+                ".log"
+            ))
+        };
+    }
+
+    macro_rules! assert_error {
+        ($fixture:literal, $error:path) => {
+            match replay(fixture!($fixture)) {
+                ConductorLaunchState::InitializeConductorError($error(_)) => {}
+                state => panic!("{}: unexpected {:?}", $fixture, state),
+            }
+        };
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn address_already_in_use() {
+        assert_error!(
+            "address_in_use",
+            InitializeConductorError::AddressAlreadyInUse
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_connection_reset() {
+        assert_error!(
+            "windows_connection_reset",
+            InitializeConductorError::AddressAlreadyInUse
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn connection_reset_is_ignored_outside_of_windows() {
+        assert!(matches!(
+            replay(fixture!("windows_connection_reset")),
+            ConductorLaunchState::ExitedEarly { .. }
+        ));
+    }
+
+    #[test]
+    fn addr_in_use() {
+        assert_error!(
+            "addr_in_use_kind",
+            InitializeConductorError::AddressAlreadyInUse
+        );
+    }
+
+    #[test]
+    fn not_a_database() {
+        assert_error!("not_a_database", InitializeConductorError::SqliteError);
+    }
+
+    #[test]
+    fn database_corrupt() {
+        assert_error!("database_corrupt", InitializeConductorError::SqliteError);
+    }
+
+    #[test]
+    fn database_locked() {
+        assert_error!("database_locked", InitializeConductorError::DatabaseLocked);
+    }
+
+    #[test]
+    fn disk_full() {
+        assert_error!("disk_full", InitializeConductorError::DiskFull);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn no_space_left_on_device() {
+        assert_error!("no_space_left", InitializeConductorError::DiskFull);
+    }
+
+    #[test]
+    fn permission_denied() {
+        assert_error!(
+            "permission_denied",
+            InitializeConductorError::PermissionDenied
+        );
+    }
+
+    #[test]
+    fn keystore_error() {
+        assert_error!("keystore_error", InitializeConductorError::KeystoreError);
+    }
+
+    #[test]
+    fn lair_error() {
+        assert_error!("lair_error", InitializeConductorError::KeystoreError);
+    }
+
+    #[test]
+    fn invalid_config() {
+        assert_error!("invalid_config", InitializeConductorError::InvalidConfig);
+    }
+
+    #[test]
+    fn unknown_panic() {
+        assert_error!("unknown_panic", InitializeConductorError::UnknownError);
+    }
+
+    #[test]
+    fn transient_warnings_before_ready_are_ignored() {
+        assert!(matches!(
+            replay(fixture!("transient_warnings_then_ready")),
+            ConductorLaunchState::Ready
+        ));
+    }
+
+    #[test]
+    fn every_pattern_is_covered_by_a_fixture() {
+        let fixtures = [
+            fixture!("address_in_use"),
+            fixture!("windows_connection_reset"),
+            fixture!("addr_in_use_kind"),
+            fixture!("not_a_database"),
+            fixture!("database_corrupt"),
+            fixture!("database_locked"),
+            fixture!("disk_full"),
+            fixture!("no_space_left"),
+            fixture!("permission_denied"),
+            fixture!("keystore_error"),
+            fixture!("lair_error"),
+            fixture!("invalid_config"),
+        ];
+        for pattern in ERROR_PATTERNS {
+            assert!(
+                fixtures.iter().any(|fixture| fixture
+                    .lines()
+                    .any(|line| pattern.contains.iter().all(|s| line.contains(s)))),
+                "no fixture for the pattern {:?}",
+                pattern.contains
+            );
+        }
+    }
+}
@@ -1,23 +1,29 @@
 use log;
-use std::{collections::HashMap, path::PathBuf};
-use tauri::api::process::{Command, CommandChild, CommandEvent};
+use std::{collections::HashMap, path::PathBuf, time::Instant};
+use tauri::{
+    api::process::{Command, CommandChild, CommandEvent},
+    async_runtime::Receiver,
+};
 
 use crate::errors::{
     AppError, AppResult, InitializeConductorError, LaunchChildError, LaunchHolochainError,
 };
 
-enum LaunchHolochainProcessState {
-    Pending,
-    InitializeConductorError(InitializeConductorError),
-    Success,
-}
+use super::{
+    classifier::{ConductorLaunchState, ConductorOutputClassifier},
+    shutdown::{register_conductor_process, unregister_conductor_process},
+};
 
+/// Spawns the holochain sidecar and waits until the conductor is ready. Returns the child process
+/// together with the receiver of its remaining output, to be handed to the supervisor.
+/// The conductor gets killed if it isn't ready by the `deadline`.
 pub async fn launch_holochain_process(
     log_level: log::Level,
     command: Command,
     conductor_config_path: PathBuf,
     password: String,
-) -> AppResult<CommandChild> {
+    deadline: Instant,
+) -> AppResult<(Receiver<CommandEvent>, CommandChild)> {
     let mut envs = HashMap::new();
     envs.insert(String::from("RUST_LOG"), String::from(log_level.as_str()));
     envs.insert(String::from("WASM_LOG"), String::from(log_level.as_str()));
@@ -36,117 +42,90 @@ pub async fn launch_holochain_process(
             ))
         })?;
 
-    let mut launch_state = LaunchHolochainProcessState::Pending;
+    let holochain_pid = holochain_child.pid();
+    register_conductor_process(holochain_pid);
 
     holochain_child
         .write(format!("{password}\n").as_bytes())
         .map_err(|err| LaunchHolochainError::ErrorWritingPassword(format!("{:?}", err)))?;
 
-    let mut fatal_error = false;
+    let mut classifier = ConductorOutputClassifier::default();
 
-    // this loop will end in still pending when the conductor crashes before being ready
-    // read events such as stdout
-    while let Some(event) = holochain_rx.recv().await {
-        match &event {
-            CommandEvent::Stdout(line) => {
-                log::info!("[HOLOCHAIN] {}", line);
-                if line.contains("Conductor ready.") {
-                    launch_state = LaunchHolochainProcessState::Success;
-                    break;
+    // read the output of the conductor until it is ready, fails or exits
+    let read_output = async {
+        loop {
+            let state = match holochain_rx.recv().await {
+                Some(CommandEvent::Stdout(line)) | Some(CommandEvent::Stderr(line)) => {
+                    log::info!("[HOLOCHAIN] {}", line);
+                    classifier.classify_line(&line)
                 }
-            }
-            CommandEvent::Stderr(line) => {
-                log::info!("[HOLOCHAIN] {}", line);
-
-                // Windows error handling:
-                // --------------------------------------
-                #[cfg(target_family = "windows")]
-                if line.contains("websocket_error_from_network=Io")
-                    && line.contains("ConnectionReset")
-                {
-                    launch_state = LaunchHolochainProcessState::InitializeConductorError(
-                        InitializeConductorError::AddressAlreadyInUse(
-                            String::from("Could not initialize Conductor from configuration: Address already in use")
-                        )
-                    );
-                    break;
+                Some(CommandEvent::Terminated(payload)) => {
+                    log::info!("[HOLOCHAIN] {:?}", payload);
+                    classifier.classify_exit(payload.code, payload.signal)
                 }
-                // --------------------------------------
-
-                // UNIX error handling:
-                // --------------------------------------
-                #[cfg(target_family = "unix")]
-                if line.contains("FATAL PANIC PanicInfo") {
-                    fatal_error = true;
+                Some(event) => {
+                    log::info!("[HOLOCHAIN] {:?}", event);
+                    ConductorLaunchState::Pending
                 }
-                #[cfg(target_family = "unix")]
-                if line.contains("Well, this is embarrassing") {
-                    // This line occurs below FATAL PANIC but may potentially also appear without FATAL PANIC PanicInfo
-                    fatal_error = true;
-                }
-                #[cfg(target_family="unix")]
-            if line.contains("Could not initialize Conductor from configuration: InterfaceError(WebsocketError(Io(Os") && line.contains("Address already in use") {
-                launch_state = LaunchHolochainProcessState::InitializeConductorError(
-                InitializeConductorError::AddressAlreadyInUse(
-                    String::from("Could not initialize Conductor from configuration: Address already in use")
-                )
-                );
-                break;
-            }
-                #[cfg(target_family="unix")]
-            if fatal_error == true && line.contains("DatabaseError(SqliteError(SqliteFailure(Error { code: NotADatabase, extended_code: 26 }, Some(\"file is not a database\"))))") {
-                launch_state = LaunchHolochainProcessState::InitializeConductorError(
-                InitializeConductorError::SqliteError(
-                    String::from("DatabaseError(SqliteError(SqliteFailure(Error { code: NotADatabase, extended_code: 26 }, Some(\"file is not a database\"))))")
-                )
-                );
-                break;
+                None => classifier.classify_exit(None, None),
+            };
+            if !matches!(state, ConductorLaunchState::Pending) {
+                break state;
             }
-                // if no known error was found between the line saying "FATAL PANIC ..." or "Well, this is embarrassing" and
-                // the line saying "Thank you kindly" it is an unknown error
-                #[cfg(target_family = "unix")]
-                if fatal_error == true && line.contains("Thank you kindly!") {
-                    launch_state = LaunchHolochainProcessState::InitializeConductorError(
-                    InitializeConductorError::UnknownError(
-                        String::from("Unknown error when trying to initialize conductor. See log file for details.")
-                    )
+        }
+    };
+
+    let launch_state = match tokio::time::timeout_at(deadline.into(), read_output).await {
+        Ok(launch_state) => launch_state,
+        Err(_) => {
+            // the conductor would otherwise keep running and keep its databases locked
+            if let Err(e) = holochain_child.kill() {
+                log::warn!(
+                    "Failed to kill the conductor that was not ready in time: {}",
+                    e
                 );
-                }
             }
-            // --------------------------------------
-            _ => {
-                log::info!("[HOLOCHAIN] {:?}", event);
-            }
-        };
-    }
-
-    log::info!("Launched holochain");
-
-    tauri::async_runtime::spawn(async move {
-        // read events such as stdout
-        while let Some(event) = holochain_rx.recv().await {
-            match event.clone() {
-                CommandEvent::Stdout(line) => println!("[HOLOCHAIN] {}", line),
-                CommandEvent::Stderr(line) => log::info!("[HOLOCHAIN] {}", line),
-                _ => log::info!("[HOLOCHAIN] {:?}", event),
-            };
+            unregister_conductor_process(holochain_pid);
+            return Err(
+                LaunchHolochainError::CouldNotConnectToConductor(String::from(
+                    "The conductor was not ready before the launch timeout",
+                ))
+                .into(),
+            );
         }
-    });
+    };
 
-    match launch_state {
-        LaunchHolochainProcessState::Success => {
-            log::info!("LaunchHolochainProcessState::Success");
-            Ok(holochain_child)
+    let error = match launch_state {
+        ConductorLaunchState::Ready => {
+            log::info!("ConductorLaunchState::Ready");
+            return Ok((holochain_rx, holochain_child));
+        }
+        ConductorLaunchState::InitializeConductorError(e) => {
+            log::info!("ConductorLaunchState::InitializeConductorError: {:?}", e);
+            e
         }
-        LaunchHolochainProcessState::InitializeConductorError(e) => {
-            log::info!("LaunchHolochainProcessState::InitializeConductorError");
-            Err(AppError::LaunchHolochainError(
-                LaunchHolochainError::CouldNotInitializeConductor(e),
+        ConductorLaunchState::ExitedEarly { code, signal } => {
+            log::info!("ConductorLaunchState::ExitedEarly");
+            InitializeConductorError::ProcessExitedEarly(format!(
+                "exit code {:?}, signal {:?}",
+                code, signal
             ))
         }
-        LaunchHolochainProcessState::Pending => {
-            log::info!("LaunchHolochainProcessState::Pending");
-            Err(AppError::LaunchHolochainError(LaunchHolochainError::ImpossibleError("LaunchHolochainProcessState still pending after launching the holochain process.".into())))
+        ConductorLaunchState::Pending => {
+            unreachable!("the loop only ends once the launch state is known")
         }
+    };
+
+    // the conductor may still be running after a recognized error
+    if let Err(e) = holochain_child.kill() {
+        log::warn!(
+            "Failed to kill the conductor after it failed to launch: {}",
+            e
+        );
     }
+    unregister_conductor_process(holochain_pid);
+
+    Err(AppError::LaunchHolochainError(
+        LaunchHolochainError::CouldNotInitializeConductor(error),
+    ))
 }
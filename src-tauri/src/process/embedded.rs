@@ -0,0 +1,53 @@
+use holochain::conductor::{
+    config::ConductorConfig, error::ConductorError, Conductor, ConductorHandle,
+};
+use holochain_keystore::MetaLairClient;
+use std::time::Instant;
+
+use crate::{
+    errors::{AppError, AppResult, InitializeConductorError, LaunchHolochainError},
+    utils::vec_to_locked,
+};
+
+/// Builds and starts the conductor inside of this process instead of spawning the holochain sidecar.
+/// Errors are returned as typed `ConductorError`s rather than being parsed from the logs, except
+/// for a taken admin port, which is reported like the sidecar reports it so that both get retried
+/// with another port.
+pub async fn launch_embedded_conductor(
+    config: ConductorConfig,
+    meta_lair_client: MetaLairClient,
+    password: String,
+    deadline: Instant,
+) -> AppResult<ConductorHandle> {
+    let build = Conductor::builder()
+        .config(config)
+        .passphrase(Some(vec_to_locked(password.into_bytes())?))
+        .with_keystore(meta_lair_client)
+        .build();
+    let conductor_handle = tokio::time::timeout_at(deadline.into(), build)
+        .await
+        .map_err(|_| {
+            LaunchHolochainError::CouldNotConnectToConductor(String::from(
+                "The conductor was not ready before the launch timeout",
+            ))
+        })?
+        .map_err(|e| match is_address_in_use(&e) {
+            true => {
+                AppError::LaunchHolochainError(LaunchHolochainError::CouldNotInitializeConductor(
+                    InitializeConductorError::AddressAlreadyInUse(format!("{:?}", e)),
+                ))
+            }
+            false => AppError::ConductorError(e),
+        })?;
+
+    log::info!("Launched embedded holochain conductor");
+
+    Ok(conductor_handle)
+}
+
+/// Whether binding the admin interface failed because its port is taken. The io error ends up
+/// wrapped in the interface errors of the conductor, so its kind is looked for in the whole error.
+fn is_address_in_use(error: &ConductorError) -> bool {
+    let error = format!("{:?}", error);
+    error.contains("AddrInUse") || error.contains("Address already in use")
+}
@@ -2,10 +2,23 @@ use std::path::PathBuf;
 use std::{collections::HashMap, time::Duration};
 use url2::Url2;
 
+use futures::{stream::poll_fn, Stream, StreamExt};
 use tauri::api::process::{Command, CommandEvent};
 
+use holochain_keystore::{lair_keystore_api::config::LairServerConfigInner, MetaLairClient};
+
+use crate::app::splash::{LaunchProgress, LaunchStage};
 use crate::config::LAIR_KEYSTORE_VERSION;
 use crate::errors::{LairKeystoreError, LaunchChildError};
+use crate::passphrase::{PassphraseMode, PassphraseProvider};
+use crate::utils::LinesWithEndings;
+
+use super::shutdown::{
+    kill_lair_process, register_lair_process, register_lair_temp_dir, unregister_lair_process,
+};
+
+/// Time lair-keystore gets to initialize or unlock the keystore
+const LAIR_STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
 
 pub async fn launch_lair_keystore_process(
     log_level: log::Level,
@@ -24,6 +37,7 @@ pub async fn launch_lair_keystore_process(
         let src_path = std::env::temp_dir().join(format!("lair.{}", uid));
         symlink::symlink_dir(keystore_path, src_path.clone())
             .map_err(|e| LairKeystoreError::ErrorCreatingSymLink(e.to_string()))?;
+        register_lair_temp_dir(src_path.clone());
         keystore_path = src_path;
 
         // overwrite connectionUrl in lair-keystore-config.yaml to symlink directory
@@ -92,35 +106,24 @@ pub async fn launch_lair_keystore_process(
                     err
                 )))
             })?;
+    let lair_pid = command_child.pid();
 
-    tauri::async_runtime::spawn(async move {
-        std::thread::sleep(Duration::from_millis(10));
-        command_child
-            .write(password.as_bytes())
-            .expect("Could not write password");
-    });
+    // the password is buffered in the stdin pipe until lair reads it, so there is no need to wait
+    command_child
+        .write(password.as_bytes())
+        .map_err(|e| LairKeystoreError::ErrorWritingPassword(e.to_string()))?;
+    register_lair_process(command_child);
 
-    let mut started = false;
-    while !started {
-        if let Some(event) = lair_rx.recv().await {
-            match event.clone() {
-                CommandEvent::Stdout(line) => {
-                    log::info!("[LAIR] {}", line);
-                    if line.contains("lair-keystore running") {
-                        started = true;
-                    }
-                }
-                CommandEvent::Stderr(line) => {
-                    log::error!("[LAIR] {}", line);
-                    if line.contains("InternalSodium") {
-                        return Err(LairKeystoreError::IncorrectPassword);
-                    }
-                }
-                _ => {
-                    log::info!("[LAIR] {:?}", event);
-                }
-            }
-        }
+    if let Err(e) = wait_for_lair_output(
+        poll_fn(|cx| lair_rx.poll_recv(cx)),
+        "lair-keystore running",
+        LAIR_STARTUP_TIMEOUT,
+    )
+    .await
+    {
+        // lair may still be running after a timeout
+        kill_lair_process();
+        return Err(e);
     }
 
     tauri::async_runtime::spawn(async move {
@@ -129,6 +132,10 @@ pub async fn launch_lair_keystore_process(
             match event.clone() {
                 CommandEvent::Stdout(line) => log::info!("[LAIR] {}", line),
                 CommandEvent::Stderr(line) => log::error!("[LAIR] {}", line),
+                CommandEvent::Terminated(payload) => {
+                    log::info!("[LAIR] {:?}", payload);
+                    unregister_lair_process(lair_pid);
+                }
                 _ => log::info!("[LAIR] {:?}", event),
             }
         }
@@ -163,10 +170,76 @@ pub async fn launch_lair_keystore_process(
     Ok(url)
 }
 
-pub async fn initialize_keystore(
+/// Runs the lair keystore server inside of this process instead of as a sidecar and connects to it.
+/// The keystore gets initialized with the given password if it does not exist yet.
+///
+/// The conductor still connects to it through its IPC socket. On Unix systems, the socket is bound
+/// to a short path in the tempdir since domain socket paths are limited in length.
+pub async fn spawn_in_proc_lair_keystore(
     keystore_dir: PathBuf,
     password: String,
-) -> Result<(), LairKeystoreError> {
+) -> Result<(MetaLairClient, Url2), LairKeystoreError> {
+    let config_path = keystore_dir.join("lair-keystore-config.yaml");
+
+    let mut config = match config_path.exists() {
+        true => {
+            let config_bytes = std::fs::read(&config_path)
+                .map_err(|e| LairKeystoreError::ErrorReadingLairConfig(e.to_string()))?;
+            LairServerConfigInner::from_bytes(&config_bytes)
+                .map_err(|e| LairKeystoreError::ErrorReadingLairConfig(e.to_string()))?
+        }
+        false => LairServerConfigInner::new(&keystore_dir, password.as_bytes().into())
+            .await
+            .map_err(|e| LairKeystoreError::OtherError(e.to_string()))?,
+    };
+
+    if cfg!(target_family = "unix") {
+        let socket_dir = std::env::temp_dir().join(format!("lair.{}", nanoid::nanoid!(13)));
+        std::fs::create_dir_all(&socket_dir)
+            .map_err(|e| LairKeystoreError::ErrorWritingLairConfig(e.to_string()))?;
+        register_lair_temp_dir(socket_dir.clone());
+        config
+            .connection_url
+            .set_path(&socket_dir.join("socket").to_string_lossy());
+    }
+
+    std::fs::write(&config_path, config.to_string())
+        .map_err(|e| LairKeystoreError::ErrorWritingLairConfig(e.to_string()))?;
+
+    let meta_lair_client = holochain_keystore::lair_keystore::spawn_lair_keystore_in_proc(
+        &config_path,
+        password.as_bytes().into(),
+    )
+    .await
+    .map_err(|e| {
+        // decrypting the runtime secrets fails if the passphrase is wrong
+        if e.to_string().contains("InternalSodium") {
+            LairKeystoreError::IncorrectPassword
+        } else {
+            LairKeystoreError::SpawnMetaLairClientError(e.to_string())
+        }
+    })?;
+
+    log::info!("Launched in-process lair-keystore");
+
+    Ok((
+        meta_lair_client,
+        Url2::parse(config.connection_url.as_str()),
+    ))
+}
+
+/// Initializes a new keystore with the passphrase from the given provider and returns the passphrase
+pub async fn initialize_keystore(
+    keystore_dir: PathBuf,
+    passphrase_provider: &dyn PassphraseProvider,
+    progress: &LaunchProgress,
+) -> Result<String, LairKeystoreError> {
+    let password = passphrase_provider
+        .passphrase(PassphraseMode::Setup)
+        .await?;
+
+    progress.start(LaunchStage::KeystoreInit);
+
     // NEW_VERSION Check whether lair-keystore version needs to get updated
     let (mut lair_rx, mut command_child) =
         Command::new_sidecar(format!("lair-keystore-v{}", LAIR_KEYSTORE_VERSION))
@@ -178,66 +251,138 @@ pub async fn initialize_keystore(
             .spawn()
             .map_err(|err| LaunchChildError::FailedToExecute(format!("{:?}", err)))?;
 
-    tauri::async_runtime::spawn(async move {
-        std::thread::sleep(Duration::from_millis(10));
-        command_child
-            .write(password.as_bytes())
-            .expect("Could not write password");
-    });
+    command_child
+        .write(password.as_bytes())
+        .map_err(|e| LairKeystoreError::ErrorWritingPassword(e.to_string()))?;
 
-    let mut started = false;
-    while !started {
-        if let Some(event) = lair_rx.recv().await {
-            match event.clone() {
-                CommandEvent::Stdout(line) => {
+    if let Err(e) = wait_for_lair_output(
+        poll_fn(|cx| lair_rx.poll_recv(cx)),
+        "lair-keystore init connection_url",
+        LAIR_STARTUP_TIMEOUT,
+    )
+    .await
+    {
+        let _ = command_child.kill();
+        return Err(e);
+    }
+
+    progress.finish(LaunchStage::KeystoreInit);
+
+    Ok(password)
+}
+
+/// Waits until lair-keystore prints the given line to stdout. Fails if the passphrase is incorrect,
+/// if the process exits before or if the line does not show up within the timeout.
+async fn wait_for_lair_output(
+    mut lair_events: impl Stream<Item = CommandEvent> + Unpin,
+    expected_line: &str,
+    timeout: Duration,
+) -> Result<(), LairKeystoreError> {
+    let wait = async {
+        loop {
+            match lair_events.next().await {
+                Some(CommandEvent::Stdout(line)) => {
                     log::info!("[LAIR] {}", line);
-                    if line.contains("lair-keystore init connection_url") {
-                        started = true;
+                    if line.contains(expected_line) {
+                        return Ok(());
                     }
                 }
-                CommandEvent::Stderr(line) => {
+                Some(CommandEvent::Stderr(line)) => {
                     log::error!("[LAIR] {}", line);
                     if line.contains("InternalSodium") {
                         return Err(LairKeystoreError::IncorrectPassword);
                     }
                 }
-                _ => {
-                    log::info!("[LAIR] {:?}", event);
+                Some(CommandEvent::Terminated(payload)) => {
+                    return Err(LairKeystoreError::ProcessExitedEarly(format!(
+                        "exit code {:?}, signal {:?}",
+                        payload.code, payload.signal
+                    )));
+                }
+                Some(event) => log::info!("[LAIR] {:?}", event),
+                None => {
+                    return Err(LairKeystoreError::ProcessExitedEarly(String::from(
+                        "the output of lair-keystore was closed",
+                    )));
                 }
             }
         }
-    }
+    };
 
-    Ok(())
+    tokio::time::timeout(timeout, wait)
+        .await
+        .map_err(|_| LairKeystoreError::StartupTimeout(timeout.as_secs()))?
 }
 
-/// Iterator yielding every line in a string. The line includes newline character(s).
-/// https://stackoverflow.com/questions/40455997/iterate-over-lines-in-a-string-including-the-newline-characters
-pub struct LinesWithEndings<'a> {
-    input: &'a str,
-}
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+    use tauri::api::process::TerminatedPayload;
+
+    use super::*;
 
-impl<'a> LinesWithEndings<'a> {
-    pub fn from(input: &'a str) -> LinesWithEndings<'a> {
-        LinesWithEndings { input: input }
+    const TIMEOUT: Duration = Duration::from_millis(200);
+
+    /// Output of a lair-keystore sidecar that stays silent after the given events
+    fn fake_sidecar(events: Vec<CommandEvent>) -> impl Stream<Item = CommandEvent> + Unpin {
+        stream::iter(events).chain(stream::pending())
     }
-}
 
-impl<'a> Iterator for LinesWithEndings<'a> {
-    type Item = &'a str;
+    #[tokio::test]
+    async fn succeeds_on_the_expected_line() {
+        let events = fake_sidecar(vec![
+            CommandEvent::Stdout(String::from("# lair-keystore starting #")),
+            CommandEvent::Stdout(String::from("# lair-keystore running #")),
+        ]);
+        assert!(
+            wait_for_lair_output(events, "lair-keystore running", TIMEOUT)
+                .await
+                .is_ok()
+        );
+    }
 
-    #[inline]
-    fn next(&mut self) -> Option<&'a str> {
-        if self.input.is_empty() {
-            return None;
-        }
-        let split = self
-            .input
-            .find('\n')
-            .map(|i| i + 1)
-            .unwrap_or(self.input.len());
-        let (line, rest) = self.input.split_at(split);
-        self.input = rest;
-        Some(line)
+    #[tokio::test]
+    async fn times_out_without_the_expected_line() {
+        let events = fake_sidecar(vec![CommandEvent::Stdout(String::from(
+            "# lair-keystore starting #",
+        ))]);
+        assert!(matches!(
+            wait_for_lair_output(events, "lair-keystore running", TIMEOUT).await,
+            Err(LairKeystoreError::StartupTimeout(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn fails_if_lair_exits_early() {
+        let events = fake_sidecar(vec![CommandEvent::Terminated(TerminatedPayload {
+            code: Some(1),
+            signal: None,
+        })]);
+        assert!(matches!(
+            wait_for_lair_output(events, "lair-keystore running", TIMEOUT).await,
+            Err(LairKeystoreError::ProcessExitedEarly(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn fails_if_the_output_is_closed() {
+        let events = stream::iter(vec![CommandEvent::Stdout(String::from(
+            "# lair-keystore starting #",
+        ))]);
+        assert!(matches!(
+            wait_for_lair_output(events, "lair-keystore running", TIMEOUT).await,
+            Err(LairKeystoreError::ProcessExitedEarly(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn detects_an_incorrect_passphrase() {
+        let events = fake_sidecar(vec![CommandEvent::Stderr(String::from(
+            "Error: InternalSodium",
+        ))]);
+        assert!(matches!(
+            wait_for_lair_output(events, "lair-keystore running", TIMEOUT).await,
+            Err(LairKeystoreError::IncorrectPassword)
+        ));
     }
 }
@@ -1,2 +1,8 @@
+pub mod classifier;
 pub mod conductor;
+#[cfg(feature = "embedded-conductor")]
+pub mod embedded;
 pub mod lair;
+pub mod readiness;
+pub mod shutdown;
+pub mod supervisor;
@@ -0,0 +1,74 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+
+const INITIAL_POLL_DELAY: Duration = Duration::from_millis(10);
+const MAX_POLL_DELAY: Duration = Duration::from_millis(500);
+
+/// Exponential backoff between polls, with jitter so that concurrent pollers don't align
+struct Backoff {
+    delay: Duration,
+}
+
+impl Backoff {
+    fn new() -> Self {
+        Backoff {
+            delay: INITIAL_POLL_DELAY,
+        }
+    }
+
+    fn next_delay(&mut self) -> Duration {
+        let jittered = self.delay.mul_f64(rand::thread_rng().gen_range(0.5..1.5));
+        self.delay = (self.delay * 2).min(MAX_POLL_DELAY);
+        jittered
+    }
+}
+
+/// Polls `check` until it succeeds or the deadline has passed, without blocking the async runtime.
+/// Returns the error of the last attempt if the deadline passes.
+pub async fn poll_until_ready<T, E, F, Fut>(
+    what: &str,
+    deadline: Instant,
+    mut check: F,
+) -> Result<T, String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: std::fmt::Display,
+{
+    let started_at = Instant::now();
+    let mut backoff = Backoff::new();
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+        match check().await {
+            Ok(value) => {
+                log::info!(
+                    "[LAUNCH] {} ready after {:?} ({} attempts)",
+                    what,
+                    started_at.elapsed(),
+                    attempts
+                );
+                return Ok(value);
+            }
+            Err(e) => {
+                let delay = backoff.next_delay();
+                if Instant::now() + delay >= deadline {
+                    return Err(format!(
+                        "{} not ready after {:?} ({} attempts): {}",
+                        what,
+                        started_at.elapsed(),
+                        attempts,
+                        e
+                    ));
+                }
+                log::debug!("[LAUNCH] {} not ready yet: {}", what, e);
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+#[cfg(unix)]
+use std::time::Instant;
+
+#[cfg(feature = "embedded-conductor")]
+use holochain::conductor::ConductorHandle;
+use tauri::{api::process::CommandChild, AppHandle};
+
+/// Time the conductor gets to finish its database writes before it gets killed
+const CONDUCTOR_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time lair-keystore gets to exit before it gets killed
+const LAIR_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[cfg_attr(not(unix), allow(dead_code))]
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The sidecars currently running, the conductor running in this process and the temp dirs
+/// created for lair-keystore's socket
+struct RunningProcesses {
+    conductor_pid: Option<u32>,
+    lair_child: Option<CommandChild>,
+    lair_temp_dirs: Vec<PathBuf>,
+    #[cfg(feature = "embedded-conductor")]
+    embedded_conductor: Option<ConductorHandle>,
+}
+
+static RUNNING_PROCESSES: Mutex<RunningProcesses> = Mutex::new(RunningProcesses {
+    conductor_pid: None,
+    lair_child: None,
+    lair_temp_dirs: Vec::new(),
+    #[cfg(feature = "embedded-conductor")]
+    embedded_conductor: None,
+});
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+pub fn register_conductor_process(pid: u32) {
+    RUNNING_PROCESSES.lock().unwrap().conductor_pid = Some(pid);
+}
+
+pub fn register_lair_process(lair_child: CommandChild) {
+    RUNNING_PROCESSES.lock().unwrap().lair_child = Some(lair_child);
+}
+
+/// Forgets the conductor process once it got killed or exited, so that the shutdown doesn't
+/// signal another process that got its pid in the meantime
+pub fn unregister_conductor_process(pid: u32) {
+    let mut running_processes = RUNNING_PROCESSES.lock().unwrap();
+    if running_processes.conductor_pid == Some(pid) {
+        running_processes.conductor_pid = None;
+    }
+}
+
+/// Forgets the lair-keystore process once it got killed or exited
+pub fn unregister_lair_process(pid: u32) {
+    let mut running_processes = RUNNING_PROCESSES.lock().unwrap();
+    if running_processes
+        .lair_child
+        .as_ref()
+        .map_or(false, |lair_child| lair_child.pid() == pid)
+    {
+        running_processes.lair_child = None;
+    }
+}
+
+/// Kills the registered lair-keystore process right away, e.g. because it stopped responding
+pub fn kill_lair_process() {
+    let lair_child = RUNNING_PROCESSES.lock().unwrap().lair_child.take();
+    if let Some(lair_child) = lair_child {
+        if let Err(e) = lair_child.kill() {
+            log::warn!("Failed to kill lair-keystore: {}", e);
+        }
+    }
+}
+
+/// Registers the conductor running in this process, so that its databases get closed before the
+/// data directories are touched
+#[cfg(feature = "embedded-conductor")]
+pub fn register_embedded_conductor(conductor_handle: ConductorHandle) {
+    RUNNING_PROCESSES.lock().unwrap().embedded_conductor = Some(conductor_handle);
+}
+
+/// Registers a `lair.*` symlink or directory in the temp dir to be removed on shutdown
+pub fn register_lair_temp_dir(path: PathBuf) {
+    RUNNING_PROCESSES.lock().unwrap().lair_temp_dirs.push(path);
+}
+
+/// Whether a shutdown is in progress, in which case exiting processes must not get restarted
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::SeqCst)
+}
+
+/// Stops the conductor with SIGTERM, or shuts down the embedded one, waiting for it to exit,
+/// then stops lair-keystore and removes its temp dirs. Any remaining sidecar gets killed at the
+/// end. Blocks until done, and can be called multiple times.
+pub fn shutdown() {
+    SHUTTING_DOWN.store(true, Ordering::SeqCst);
+
+    #[cfg(feature = "embedded-conductor")]
+    {
+        let embedded_conductor = RUNNING_PROCESSES.lock().unwrap().embedded_conductor.take();
+        if let Some(conductor_handle) = embedded_conductor {
+            log::info!("Stopping the embedded holochain conductor...");
+            stop_embedded_conductor(conductor_handle);
+        }
+    }
+
+    let (conductor_pid, lair_pid, lair_temp_dirs) = {
+        let mut running_processes = RUNNING_PROCESSES.lock().unwrap();
+        (
+            running_processes.conductor_pid.take(),
+            running_processes
+                .lair_child
+                .take()
+                .map(|lair_child| lair_child.pid()),
+            std::mem::take(&mut running_processes.lair_temp_dirs),
+        )
+    };
+
+    if let Some(pid) = conductor_pid {
+        log::info!("Stopping holochain...");
+        terminate(pid, "holochain", CONDUCTOR_SHUTDOWN_TIMEOUT);
+    }
+    if let Some(pid) = lair_pid {
+        log::info!("Stopping lair-keystore...");
+        terminate(pid, "lair-keystore", LAIR_SHUTDOWN_TIMEOUT);
+    }
+    for path in lair_temp_dirs {
+        if let Err(e) = remove_temp_dir(&path) {
+            log::warn!("Failed to remove lair temp dir {:?}: {}", path, e);
+        }
+    }
+
+    // kills whatever is left, on Windows this is also where the sidecars get stopped
+    tauri::api::process::kill_children();
+}
+
+/// Shuts down in the background so that windows stay responsive, then exits the app
+pub fn shutdown_and_exit(app_handle: AppHandle) {
+    std::thread::spawn(move || {
+        shutdown();
+        app_handle.exit(0);
+    });
+}
+
+/// Shuts down in the background so that windows stay responsive, then restarts the app
+pub fn shutdown_and_restart(app_handle: AppHandle) {
+    std::thread::spawn(move || {
+        shutdown();
+        app_handle.restart();
+    });
+}
+
+#[cfg(feature = "embedded-conductor")]
+fn stop_embedded_conductor(conductor_handle: ConductorHandle) {
+    let result = tauri::async_runtime::block_on(async {
+        tokio::time::timeout(CONDUCTOR_SHUTDOWN_TIMEOUT, conductor_handle.shutdown()).await
+    });
+    match result {
+        Ok(Ok(Ok(()))) => log::info!("holochain stopped"),
+        Ok(result) => log::warn!("holochain did not stop cleanly: {:?}", result),
+        Err(_) => log::warn!(
+            "holochain did not stop within {:?}",
+            CONDUCTOR_SHUTDOWN_TIMEOUT
+        ),
+    }
+}
+
+#[cfg(unix)]
+fn terminate(pid: u32, name: &str, timeout: Duration) {
+    let pid = pid as libc::pid_t;
+    if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+        log::info!("{} has already exited", name);
+        return;
+    }
+
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        // signal 0 only checks whether the process still exists
+        if unsafe { libc::kill(pid, 0) } != 0 {
+            log::info!("{} stopped", name);
+            return;
+        }
+        std::thread::sleep(SHUTDOWN_POLL_INTERVAL);
+    }
+
+    log::warn!("{} did not stop within {:?}. Killing it.", name, timeout);
+    unsafe {
+        libc::kill(pid, libc::SIGKILL);
+    }
+}
+
+/// Windows has no SIGTERM, the sidecars get killed by `kill_children` instead
+#[cfg(not(unix))]
+fn terminate(_pid: u32, name: &str, _timeout: Duration) {
+    log::info!("{} will get killed", name);
+}
+
+/// Removes a lair temp dir without following it if it is a symlink to the keystore
+fn remove_temp_dir(path: &PathBuf) -> std::io::Result<()> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if metadata.file_type().is_symlink() {
+        symlink::remove_symlink_dir(path)
+    } else {
+        std::fs::remove_dir_all(path)
+    }
+}
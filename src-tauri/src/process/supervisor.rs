@@ -0,0 +1,304 @@
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use holochain::conductor::config::{ConductorConfig, KeystoreConfig};
+use holochain_client::AdminWebsocket;
+use serde::Serialize;
+use tauri::{
+    api::process::{Command, CommandChild, CommandEvent},
+    async_runtime::Receiver,
+    AppHandle, Manager,
+};
+use url2::Url2;
+
+use crate::{
+    app_state::AppState,
+    config::{KeystoreMode, HOLOCHAIN_VERSION},
+    errors::{AppError, AppResult, LairKeystoreError, LaunchHolochainError},
+    process::{
+        conductor::launch_holochain_process,
+        lair::launch_lair_keystore_process,
+        shutdown::{is_shutting_down, kill_lair_process, unregister_conductor_process},
+    },
+};
+
+/// Interval between two health checks of the admin websocket
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Time after which a health check is considered failed
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Number of consecutive failed health checks after which the conductor gets restarted
+const MAX_FAILED_HEALTH_CHECKS: u32 = 3;
+
+/// Number of consecutive failed restarts after which the supervisor gives up
+const MAX_RESTART_ATTEMPTS: u32 = 6;
+
+const INITIAL_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+
+/// Name of the Tauri event carrying the `ConductorStatus`
+pub const CONDUCTOR_STATUS_EVENT: &str = "conductor-status";
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ConductorStatus {
+    Running,
+    Reconnecting { attempt: u32, reason: String },
+    Failed { reason: String },
+}
+
+/// A running holochain sidecar and everything needed to restart it
+pub struct SupervisedConductor {
+    pub holochain_rx: Receiver<CommandEvent>,
+    pub holochain_child: CommandChild,
+    pub conductor_config_path: PathBuf,
+    pub admin_port: u16,
+    pub password: String,
+    pub log_level: log::Level,
+    pub keystore_mode: KeystoreMode,
+    pub keystore_dir: PathBuf,
+    pub lair_url: Url2,
+    /// Time a restarted conductor gets to become ready
+    pub launch_timeout: Duration,
+}
+
+enum ConductorFailure {
+    Exited(String),
+    Unhealthy,
+}
+
+/// Watches the holochain sidecar for exits and failing admin websocket health checks and restarts it
+/// (and the lair keystore if necessary) with exponential backoff. Status changes are emitted to all
+/// windows as `conductor-status` events.
+pub fn supervise_conductor(app_handle: AppHandle, conductor: SupervisedConductor) {
+    tauri::async_runtime::spawn(async move {
+        let SupervisedConductor {
+            mut holochain_rx,
+            mut holochain_child,
+            conductor_config_path,
+            admin_port,
+            password,
+            log_level,
+            keystore_mode,
+            keystore_dir,
+            mut lair_url,
+            launch_timeout,
+        } = conductor;
+
+        loop {
+            let failure = watch_conductor(&mut holochain_rx, admin_port).await;
+            if is_shutting_down() {
+                log::info!("[SUPERVISOR] The conductor was stopped for the shutdown.");
+                return;
+            }
+            let holochain_pid = holochain_child.pid();
+            let reason = match failure {
+                ConductorFailure::Exited(reason) => reason,
+                ConductorFailure::Unhealthy => {
+                    if let Err(e) = holochain_child.kill() {
+                        log::error!("Failed to kill the unresponsive conductor: {}", e);
+                    }
+                    String::from("The conductor stopped responding")
+                }
+            };
+            unregister_conductor_process(holochain_pid);
+            log::error!("[SUPERVISOR] {}. Restarting the conductor.", reason);
+
+            let mut attempt = 0;
+            let restarted = loop {
+                attempt += 1;
+                if attempt > MAX_RESTART_ATTEMPTS {
+                    break None;
+                }
+                if is_shutting_down() {
+                    return;
+                }
+                emit_status(
+                    &app_handle,
+                    ConductorStatus::Reconnecting {
+                        attempt,
+                        reason: reason.clone(),
+                    },
+                );
+                tokio::time::sleep(restart_delay(attempt)).await;
+
+                match restart_conductor(
+                    &app_handle,
+                    &conductor_config_path,
+                    &password,
+                    log_level,
+                    keystore_mode,
+                    &keystore_dir,
+                    &mut lair_url,
+                    launch_timeout,
+                )
+                .await
+                {
+                    Ok(process) => break Some(process),
+                    Err(e) => log::error!(
+                        "[SUPERVISOR] Restart attempt {}/{} failed: {}",
+                        attempt,
+                        MAX_RESTART_ATTEMPTS,
+                        e
+                    ),
+                }
+            };
+
+            match restarted {
+                Some((rx, child)) => {
+                    log::info!("[SUPERVISOR] Conductor restarted.");
+                    holochain_rx = rx;
+                    holochain_child = child;
+                    emit_status(&app_handle, ConductorStatus::Running);
+                }
+                None => {
+                    log::error!("[SUPERVISOR] Giving up restarting the conductor.");
+                    emit_status(&app_handle, ConductorStatus::Failed { reason });
+                    return;
+                }
+            }
+        }
+    });
+}
+
+/// Logs the output of the conductor until it exits or stops responding to health checks
+async fn watch_conductor(
+    holochain_rx: &mut Receiver<CommandEvent>,
+    admin_port: u16,
+) -> ConductorFailure {
+    let mut health_check_interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+    let mut failed_health_checks = 0;
+
+    loop {
+        tokio::select! {
+            event = holochain_rx.recv() => match event {
+                Some(CommandEvent::Stdout(line)) => log::info!("[HOLOCHAIN] {}", line),
+                Some(CommandEvent::Stderr(line)) => log::info!("[HOLOCHAIN] {}", line),
+                Some(CommandEvent::Terminated(payload)) => {
+                    return ConductorFailure::Exited(format!(
+                        "The conductor exited with code {:?} (signal {:?})",
+                        payload.code, payload.signal
+                    ));
+                }
+                Some(event) => log::info!("[HOLOCHAIN] {:?}", event),
+                None => return ConductorFailure::Exited(String::from("The conductor exited")),
+            },
+            _ = health_check_interval.tick() => {
+                if check_admin_ws_health(admin_port).await {
+                    failed_health_checks = 0;
+                } else {
+                    failed_health_checks += 1;
+                    log::warn!(
+                        "[SUPERVISOR] Admin websocket health check failed ({}/{})",
+                        failed_health_checks,
+                        MAX_FAILED_HEALTH_CHECKS
+                    );
+                    if failed_health_checks >= MAX_FAILED_HEALTH_CHECKS {
+                        return ConductorFailure::Unhealthy;
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn check_admin_ws_health(admin_port: u16) -> bool {
+    let check = async {
+        match AdminWebsocket::connect(SocketAddr::from(([127, 0, 0, 1], admin_port))).await {
+            Ok(admin_ws) => admin_ws.list_apps(None).await.is_ok(),
+            Err(_) => false,
+        }
+    };
+    tokio::time::timeout(HEALTH_CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or(false)
+}
+
+fn restart_delay(attempt: u32) -> Duration {
+    INITIAL_RESTART_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(MAX_RESTART_DELAY)
+}
+
+fn emit_status(app_handle: &AppHandle, status: ConductorStatus) {
+    if let Err(e) = app_handle.emit_all(CONDUCTOR_STATUS_EVENT, status) {
+        log::error!("Failed to emit conductor status: {}", e);
+    }
+}
+
+/// Restarts the lair keystore if it is not reachable anymore, then restarts the conductor
+async fn restart_conductor(
+    app_handle: &AppHandle,
+    conductor_config_path: &PathBuf,
+    password: &String,
+    log_level: log::Level,
+    keystore_mode: KeystoreMode,
+    keystore_dir: &PathBuf,
+    lair_url: &mut Url2,
+    launch_timeout: Duration,
+) -> AppResult<(Receiver<CommandEvent>, CommandChild)> {
+    let lair_reachable = holochain_keystore::lair_keystore::spawn_lair_keystore(
+        lair_url.clone(),
+        password.as_bytes().into(),
+    )
+    .await
+    .is_ok();
+
+    if !lair_reachable && keystore_mode == KeystoreMode::Sidecar {
+        log::warn!("[SUPERVISOR] lair-keystore is not reachable. Restarting it.");
+        // the previous lair-keystore may be hung instead of gone
+        kill_lair_process();
+        let new_lair_url =
+            launch_lair_keystore_process(log_level, keystore_dir.clone(), password.clone()).await?;
+        let meta_lair_client = holochain_keystore::lair_keystore::spawn_lair_keystore(
+            new_lair_url.clone(),
+            password.as_bytes().into(),
+        )
+        .await
+        .map_err(|e| LairKeystoreError::SpawnMetaLairClientError(format!("{}", e)))?;
+
+        update_conductor_keystore_url(conductor_config_path, &new_lair_url)?;
+        if let Some(state) = app_handle.try_state::<AppState>() {
+            *state.meta_lair_client.lock().await = meta_lair_client;
+            *state.lair_url.lock().await = new_lair_url.clone();
+        }
+        *lair_url = new_lair_url;
+    }
+
+    let command =
+        Command::new_sidecar(format!("holochain-v{}", HOLOCHAIN_VERSION)).map_err(|err| {
+            AppError::LaunchHolochainError(LaunchHolochainError::SidecarBinaryCommandError(
+                format!("{}", err),
+            ))
+        })?;
+
+    launch_holochain_process(
+        log_level,
+        command,
+        conductor_config_path.clone(),
+        password.clone(),
+        Instant::now() + launch_timeout,
+    )
+    .await
+}
+
+/// Points the conductor config to the connection url of a restarted lair keystore
+fn update_conductor_keystore_url(
+    conductor_config_path: &PathBuf,
+    lair_url: &Url2,
+) -> AppResult<()> {
+    let config_string = std::fs::read_to_string(conductor_config_path)?;
+    let mut config: ConductorConfig = serde_yaml::from_str(&config_string)
+        .map_err(|e| LaunchHolochainError::FailedToOverwriteConfig(e.to_string()))?;
+    config.keystore = KeystoreConfig::LairServer {
+        connection_url: lair_url.clone(),
+    };
+    let config_string = serde_yaml::to_string(&config)
+        .map_err(|e| LaunchHolochainError::FailedToOverwriteConfig(e.to_string()))?;
+    std::fs::write(conductor_config_path, config_string)?;
+    Ok(())
+}
@@ -0,0 +1,447 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use holochain_keystore::lair_keystore_api::{
+    config::LairServerConfigInner,
+    dependencies::sodoken::{self, BufReadSized, BufWriteSized},
+    types::SecretDataSized,
+};
+use holochain_sqlite::{db::DbKey, rusqlite::Connection};
+
+use crate::{
+    app_state::filesystem::AppFileSystem,
+    errors::{AppError, AppResult, LairKeystoreError},
+    utils::{copy_dir_all, vec_to_locked},
+};
+
+/// Name of the directory in the profile data dir holding a copy of the keystore and conductor
+/// directories while the passphrase is being changed
+const REKEY_BACKUP_DIR: &str = ".passphrase-change-backup";
+
+/// Written to the backup directory once both copies are complete. A backup without it was
+/// interrupted while copying and must never be restored over the live directories.
+const BACKUP_COMPLETE_MARKER: &str = "backup-complete";
+
+/// Changes the passphrase of the lair keystore and the encrypted conductor databases of a profile.
+/// Both holochain and lair must not be running.
+///
+/// A copy of the keystore and conductor directories is made before anything is touched. If any step
+/// fails, the copy is restored so that the old passphrase remains valid for both of them.
+pub async fn change_passphrase(
+    fs: &AppFileSystem,
+    old_passphrase: String,
+    new_passphrase: String,
+) -> AppResult<()> {
+    let backup_dir = fs.profile_data_dir.join(REKEY_BACKUP_DIR);
+    if backup_dir.exists() {
+        std::fs::remove_dir_all(&backup_dir)?;
+    }
+    std::fs::create_dir_all(&backup_dir)?;
+    copy_dir_all(&fs.keystore_dir(), &backup_dir.join("keystore"))?;
+    copy_dir_all(&fs.conductor_dir(), &backup_dir.join("conductor"))?;
+    sync_dir_all(&backup_dir)?;
+    let mut marker = File::create(backup_dir.join(BACKUP_COMPLETE_MARKER))?;
+    marker.write_all(b"")?;
+    marker.sync_all()?;
+
+    let result = async {
+        rekey_lair_keystore(&fs.keystore_dir(), &old_passphrase, &new_passphrase).await?;
+        rekey_conductor_databases(&fs.conductor_dir(), &old_passphrase, &new_passphrase).await
+    }
+    .await;
+
+    match result {
+        Ok(()) => {
+            remove_backup(&backup_dir)?;
+            log::info!("Changed the passphrase of the keystore and the conductor databases.");
+            Ok(())
+        }
+        Err(e) => {
+            log::error!("Failed to change the passphrase, rolling back: {}", e);
+            restore_backup(fs)?;
+            Err(e)
+        }
+    }
+}
+
+/// Restores the keystore and conductor directories if a passphrase change got interrupted,
+/// e.g. because the app crashed in the middle of it
+pub fn recover_interrupted_passphrase_change(fs: &AppFileSystem) -> AppResult<()> {
+    let backup_dir = fs.profile_data_dir.join(REKEY_BACKUP_DIR);
+    if !backup_dir.exists() {
+        return Ok(());
+    }
+    if backup_dir.join(BACKUP_COMPLETE_MARKER).exists() {
+        log::warn!("Found the backup of an interrupted passphrase change. Restoring it.");
+        restore_backup(fs)?;
+    } else {
+        // the change was interrupted while copying, before anything was re-keyed
+        log::warn!("Found an incomplete backup of a passphrase change. Deleting it.");
+        std::fs::remove_dir_all(&backup_dir)?;
+    }
+    Ok(())
+}
+
+fn restore_backup(fs: &AppFileSystem) -> AppResult<()> {
+    let backup_dir = fs.profile_data_dir.join(REKEY_BACKUP_DIR);
+    for (backup, target) in [
+        (backup_dir.join("keystore"), fs.keystore_dir()),
+        (backup_dir.join("conductor"), fs.conductor_dir()),
+    ] {
+        // already moved back by a restore that got interrupted itself
+        if !backup.exists() {
+            continue;
+        }
+        if target.exists() {
+            std::fs::remove_dir_all(&target)?;
+        }
+        std::fs::rename(&backup, &target)?;
+    }
+    remove_backup(&backup_dir)?;
+    Ok(())
+}
+
+/// Deletes the backup directory. The marker goes first, so that a crash in the middle of the
+/// deletion leaves a backup that is known to be incomplete and never gets restored.
+fn remove_backup(backup_dir: &Path) -> std::io::Result<()> {
+    let marker = backup_dir.join(BACKUP_COMPLETE_MARKER);
+    if marker.exists() {
+        std::fs::remove_file(&marker)?;
+        sync_dir(backup_dir)?;
+    }
+    std::fs::remove_dir_all(backup_dir)
+}
+
+/// Re-encrypts the runtime secrets in the lair-keystore-config.yaml with the new passphrase.
+/// The secrets themselves stay the same, which keeps the store file and the seeds in it valid.
+async fn rekey_lair_keystore(
+    keystore_dir: &Path,
+    old_passphrase: &str,
+    new_passphrase: &str,
+) -> AppResult<()> {
+    let config_path = keystore_dir.join("lair-keystore-config.yaml");
+    let config_bytes = std::fs::read(&config_path)
+        .map_err(|e| LairKeystoreError::ErrorReadingLairConfig(e.to_string()))?;
+    let mut config = LairServerConfigInner::from_bytes(&config_bytes)
+        .map_err(|e| LairKeystoreError::ErrorReadingLairConfig(e.to_string()))?;
+
+    let old_salt: [u8; 16] = *config.runtime_secrets_salt.0;
+    let (old_ctx_secret, old_id_secret) = derive_runtime_secret_keys(
+        old_passphrase,
+        BufReadSized::from(old_salt),
+        config.runtime_secrets_ops_limit,
+        config.runtime_secrets_mem_limit,
+    )
+    .await?;
+    let context_key = config
+        .runtime_secrets_context_key
+        .decrypt(old_ctx_secret)
+        .await
+        .map_err(|_| LairKeystoreError::IncorrectPassword)?;
+    let id_seed = config
+        .runtime_secrets_id_seed
+        .decrypt(old_id_secret)
+        .await
+        .map_err(|_| LairKeystoreError::IncorrectPassword)?;
+
+    let salt = BufWriteSized::<16>::new_no_lock();
+    sodoken::random::bytes_buf(salt.clone())
+        .await
+        .map_err(|e| LairKeystoreError::OtherError(e.to_string()))?;
+    let new_salt: [u8; 16] = *salt.read_lock_sized();
+    let (new_ctx_secret, new_id_secret) = derive_runtime_secret_keys(
+        new_passphrase,
+        BufReadSized::from(new_salt),
+        config.runtime_secrets_ops_limit,
+        config.runtime_secrets_mem_limit,
+    )
+    .await?;
+
+    config.runtime_secrets_salt = new_salt.into();
+    config.runtime_secrets_context_key = SecretDataSized::encrypt(new_ctx_secret, context_key)
+        .await
+        .map_err(|e| LairKeystoreError::OtherError(e.to_string()))?;
+    config.runtime_secrets_id_seed = SecretDataSized::encrypt(new_id_secret, id_seed)
+        .await
+        .map_err(|e| LairKeystoreError::OtherError(e.to_string()))?;
+
+    std::fs::write(&config_path, config.to_string())
+        .map_err(|e| LairKeystoreError::ErrorWritingLairConfig(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Derives the keys encrypting lair's runtime secrets from a passphrase, the same way
+/// lair-keystore does when it unlocks its config
+async fn derive_runtime_secret_keys(
+    passphrase: &str,
+    salt: BufReadSized<16>,
+    ops_limit: u32,
+    mem_limit: u32,
+) -> AppResult<(BufReadSized<32>, BufReadSized<32>)> {
+    let passphrase = {
+        let buf = sodoken::BufWrite::new_mem_locked(passphrase.len())?;
+        buf.write_lock().copy_from_slice(passphrase.as_bytes());
+        buf.to_read()
+    };
+
+    let pre_secret = BufWriteSized::<32>::new_mem_locked()?;
+    sodoken::hash::argon2id::hash(pre_secret.clone(), passphrase, salt, ops_limit, mem_limit)
+        .await
+        .map_err(|e| LairKeystoreError::OtherError(e.to_string()))?;
+
+    let ctx_secret = BufWriteSized::<32>::new_mem_locked()?;
+    sodoken::kdf::derive_from_key(
+        ctx_secret.clone(),
+        42,
+        *b"CtxSecKy",
+        pre_secret.to_read_sized(),
+    )
+    .map_err(|e| LairKeystoreError::OtherError(e.to_string()))?;
+
+    let id_secret = BufWriteSized::<32>::new_mem_locked()?;
+    sodoken::kdf::derive_from_key(
+        id_secret.clone(),
+        142,
+        *b"IdnSecKy",
+        pre_secret.to_read_sized(),
+    )
+    .map_err(|e| LairKeystoreError::OtherError(e.to_string()))?;
+
+    Ok((ctx_secret.to_read_sized(), id_secret.to_read_sized()))
+}
+
+/// Replaces the key of every database in the conductor's data directory with a key locked by
+/// the new passphrase and updates the locked key file accordingly
+async fn rekey_conductor_databases(
+    conductor_dir: &Path,
+    old_passphrase: &str,
+    new_passphrase: &str,
+) -> AppResult<()> {
+    let databases_dir = conductor_dir.join("databases");
+    let db_key_path = databases_dir.join("db.key");
+    if !db_key_path.exists() {
+        // the conductor has never been started, there is nothing to re-key
+        return Ok(());
+    }
+
+    let locked = std::fs::read_to_string(&db_key_path)?;
+    let old_key = DbKey::load(locked, vec_to_locked(old_passphrase.as_bytes().to_vec())?)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Failed to unlock db.key: {}", e)))?;
+    let new_key = DbKey::generate(vec_to_locked(new_passphrase.as_bytes().to_vec())?)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Failed to generate db.key: {}", e)))?;
+
+    for database in find_databases(&databases_dir)? {
+        log::info!("Re-keying database {:?}", database);
+        let conn = Connection::open(&database)
+            .map_err(|e| AppError::DatabaseError(format!("{:?}: {}", database, e)))?;
+        conn.execute_batch(&format!(
+            "PRAGMA key = \"x'{}'\"; PRAGMA rekey = \"x'{}'\";",
+            hex_key(&old_key),
+            hex_key(&new_key),
+        ))
+        .map_err(|e| AppError::DatabaseError(format!("{:?}: {}", database, e)))?;
+    }
+
+    std::fs::write(&db_key_path, new_key.locked)?;
+
+    Ok(())
+}
+
+fn hex_key(key: &DbKey) -> String {
+    key.unlocked
+        .read_lock()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn find_databases(dir: &Path) -> AppResult<Vec<PathBuf>> {
+    let mut databases = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            databases.append(&mut find_databases(&path)?);
+        } else if path.extension().map_or(false, |ext| ext == "sqlite3") {
+            databases.push(path);
+        }
+    }
+    Ok(databases)
+}
+
+/// Flushes the entries of a directory to disk, so that a removed file stays removed after a crash.
+/// Directories can't be opened for that on Windows, where NTFS journals these changes anyway.
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Flushes the files of a directory to disk recursively
+fn sync_dir_all(dir: &Path) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            sync_dir_all(&entry.path())?;
+        } else if file_type.is_file() {
+            File::open(entry.path())?.sync_all()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use holochain_keystore::lair_keystore_api::prelude::LairEntryInfo;
+    use holochain_sqlite::{
+        db::{DbKindConductor, DbWrite, PoolConfig},
+        error::DatabaseResult,
+    };
+
+    use super::*;
+    use crate::process::lair::spawn_in_proc_lair_keystore;
+
+    fn test_fs(dir: &Path) -> AppFileSystem {
+        AppFileSystem {
+            app_data_dir: dir.to_path_buf(),
+            app_config_dir: dir.join("config"),
+            profile_data_dir: dir.join("default"),
+            profile_config_dir: dir.join("config").join("default"),
+            profile_log_dir: dir.join("logs"),
+        }
+    }
+
+    async fn open_conductor_db(
+        fs: &AppFileSystem,
+        passphrase: &str,
+    ) -> DatabaseResult<DbWrite<DbKindConductor>> {
+        let databases_dir = fs.conductor_dir().join("databases");
+        let locked = std::fs::read_to_string(databases_dir.join("db.key")).unwrap();
+        let key = DbKey::load(
+            locked,
+            vec_to_locked(passphrase.as_bytes().to_vec()).unwrap(),
+        )
+        .await?;
+        DbWrite::open_with_pool_config(
+            &databases_dir,
+            DbKindConductor,
+            PoolConfig {
+                key,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Sets up a keystore and a conductor database through lair and holochain_sqlite themselves,
+    /// so that a change in how they derive their keys from the passphrase makes this fail
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rekeyed_keystore_and_databases_open_with_the_new_passphrase() {
+        let tmp = tempfile::tempdir().unwrap();
+        let fs = test_fs(tmp.path());
+        std::fs::create_dir_all(fs.keystore_dir()).unwrap();
+
+        let (meta_lair_client, _) =
+            spawn_in_proc_lair_keystore(fs.keystore_dir(), String::from("old passphrase"))
+                .await
+                .unwrap();
+        let agent_key = meta_lair_client.new_sign_keypair_random().await.unwrap();
+        meta_lair_client.shutdown().await.unwrap();
+
+        let databases_dir = fs.conductor_dir().join("databases");
+        std::fs::create_dir_all(&databases_dir).unwrap();
+        let key = DbKey::generate(vec_to_locked(b"old passphrase".to_vec()).unwrap())
+            .await
+            .unwrap();
+        std::fs::write(databases_dir.join("db.key"), &key.locked).unwrap();
+        let db = open_conductor_db(&fs, "old passphrase").await.unwrap();
+        db.write_async(|txn| -> DatabaseResult<()> {
+            txn.execute("CREATE TABLE rekey_test (value TEXT)", [])?;
+            txn.execute("INSERT INTO rekey_test VALUES ('survives')", [])?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        drop(db);
+
+        change_passphrase(
+            &fs,
+            String::from("old passphrase"),
+            String::from("new passphrase"),
+        )
+        .await
+        .unwrap();
+        assert!(!fs.profile_data_dir.join(REKEY_BACKUP_DIR).exists());
+
+        let db = open_conductor_db(&fs, "new passphrase").await.unwrap();
+        let value = db
+            .read_async(|txn| -> DatabaseResult<String> {
+                Ok(txn.query_row("SELECT value FROM rekey_test", [], |row| row.get(0))?)
+            })
+            .await
+            .unwrap();
+        assert_eq!(value, "survives");
+        assert!(open_conductor_db(&fs, "old passphrase").await.is_err());
+
+        assert!(matches!(
+            spawn_in_proc_lair_keystore(fs.keystore_dir(), String::from("old passphrase")).await,
+            Err(LairKeystoreError::IncorrectPassword)
+        ));
+        let (meta_lair_client, _) =
+            spawn_in_proc_lair_keystore(fs.keystore_dir(), String::from("new passphrase"))
+                .await
+                .unwrap();
+        let entries = meta_lair_client.lair_client().list_entries().await.unwrap();
+        assert!(entries.iter().any(|entry| matches!(
+            entry,
+            LairEntryInfo::Seed { seed_info, .. }
+                if seed_info.ed25519_pub_key[..] == agent_key.get_raw_32()[..]
+        )));
+        meta_lair_client.shutdown().await.unwrap();
+    }
+
+    #[test]
+    fn incomplete_backup_is_deleted_instead_of_restored() {
+        let tmp = tempfile::tempdir().unwrap();
+        let fs = test_fs(tmp.path());
+        std::fs::create_dir_all(fs.keystore_dir()).unwrap();
+        std::fs::write(fs.keystore_dir().join("store"), "live").unwrap();
+        let backup_keystore_dir = fs.profile_data_dir.join(REKEY_BACKUP_DIR).join("keystore");
+        std::fs::create_dir_all(&backup_keystore_dir).unwrap();
+
+        recover_interrupted_passphrase_change(&fs).unwrap();
+
+        assert!(!fs.profile_data_dir.join(REKEY_BACKUP_DIR).exists());
+        assert_eq!(
+            std::fs::read_to_string(fs.keystore_dir().join("store")).unwrap(),
+            "live"
+        );
+    }
+
+    #[test]
+    fn complete_backup_is_restored_and_removed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let fs = test_fs(tmp.path());
+        std::fs::create_dir_all(fs.keystore_dir()).unwrap();
+        std::fs::write(fs.keystore_dir().join("store"), "rekeyed").unwrap();
+        let backup_dir = fs.profile_data_dir.join(REKEY_BACKUP_DIR);
+        std::fs::create_dir_all(backup_dir.join("keystore")).unwrap();
+        std::fs::write(backup_dir.join("keystore").join("store"), "backup").unwrap();
+        std::fs::write(backup_dir.join(BACKUP_COMPLETE_MARKER), "").unwrap();
+
+        recover_interrupted_passphrase_change(&fs).unwrap();
+
+        assert!(!backup_dir.exists());
+        assert_eq!(
+            std::fs::read_to_string(fs.keystore_dir().join("store")).unwrap(),
+            "backup"
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use serde::Serialize;
+
+use crate::{
+    app_state::filesystem::{AppFileSystem, Profile},
+    errors::{AppError, AppResult},
+    utils::{dir_name, version_of_dir},
+};
+
+/// Directory next to the version directories that holds the archived ones
+const ARCHIVES_DIR_NAME: &str = "archives";
+
+const ARCHIVE_EXTENSION: &str = ".tar.gz";
+
+/// Disk usage of the data directories of all versions of the app and of their archives
+#[derive(Serialize, Debug, Clone)]
+pub struct StorageUsage {
+    pub versions: Vec<VersionStorage>,
+    pub archives: Vec<ArchiveStorage>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct VersionStorage {
+    /// Breaking version the data directory belongs to, e.g. `0.2.x`
+    pub version: String,
+    pub path: String,
+    pub size_bytes: u64,
+    pub in_use: bool,
+    pub profiles: Vec<ProfileStorage>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProfileStorage {
+    pub profile: Profile,
+    pub size_bytes: u64,
+    pub in_use: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ArchiveStorage {
+    pub file_name: String,
+    pub size_bytes: u64,
+}
+
+pub fn storage_usage(fs: &AppFileSystem) -> AppResult<StorageUsage> {
+    let root_dir = root_dir(fs)?;
+
+    let mut versions = Vec::new();
+    // the root may hold other directories, like the archives or the logs on Windows
+    for version_dir in sub_dirs(&root_dir)? {
+        if version_of_dir(&version_dir).is_none() {
+            continue;
+        }
+        let version = dir_name(&version_dir);
+        let profiles = sub_dirs(&version_dir)?
+            .into_iter()
+            .map(|profile_dir| ProfileStorage {
+                profile: dir_name(&profile_dir),
+                size_bytes: dir_size(&profile_dir),
+                in_use: is_in_use(fs, &profile_dir),
+            })
+            .collect();
+        versions.push(VersionStorage {
+            version,
+            path: version_dir.to_string_lossy().to_string(),
+            size_bytes: dir_size(&version_dir),
+            in_use: is_in_use(fs, &version_dir),
+            profiles,
+        });
+    }
+    versions.sort_by(|a, b| a.version.cmp(&b.version));
+
+    let archives_dir = root_dir.join(ARCHIVES_DIR_NAME);
+    let mut archives = Vec::new();
+    if archives_dir.exists() {
+        for entry in std::fs::read_dir(&archives_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                archives.push(ArchiveStorage {
+                    file_name: entry.file_name().to_string_lossy().to_string(),
+                    size_bytes: entry.metadata()?.len(),
+                });
+            }
+        }
+    }
+    archives.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+
+    Ok(StorageUsage { versions, archives })
+}
+
+/// Compresses the data directory of a version, or of one of its profiles, into the archives
+/// directory and deletes it afterwards
+pub fn archive_storage(
+    fs: &AppFileSystem,
+    version: &str,
+    profile: Option<&str>,
+) -> AppResult<PathBuf> {
+    let target = resolve_removable_dir(fs, version, profile)?;
+
+    let archives_dir = root_dir(fs)?.join(ARCHIVES_DIR_NAME);
+    std::fs::create_dir_all(&archives_dir)?;
+    let name = match profile {
+        Some(profile) => format!("{}-{}", version, profile),
+        None => version.to_string(),
+    };
+    let archive_path = archives_dir.join(format!(
+        "{}-{}{}",
+        name,
+        chrono::Utc::now().format("%Y%m%d%H%M%S"),
+        ARCHIVE_EXTENSION
+    ));
+
+    log::info!("Archiving {:?} to {:?}", target, archive_path);
+    if let Err(e) = write_archive(&target, &name, &archive_path) {
+        // don't leave a broken archive behind
+        let _ = std::fs::remove_file(&archive_path);
+        return Err(e.into());
+    }
+    std::fs::remove_dir_all(&target)?;
+
+    Ok(archive_path)
+}
+
+/// Deletes the data directory of a version or of one of its profiles for good
+pub fn delete_storage(fs: &AppFileSystem, version: &str, profile: Option<&str>) -> AppResult<()> {
+    let target = resolve_removable_dir(fs, version, profile)?;
+    log::warn!("Deleting {:?}", target);
+    std::fs::remove_dir_all(&target)?;
+    Ok(())
+}
+
+pub fn delete_archive(fs: &AppFileSystem, file_name: &str) -> AppResult<()> {
+    check_name(file_name)?;
+    let archive_path = root_dir(fs)?.join(ARCHIVES_DIR_NAME).join(file_name);
+    if !archive_path.is_file() || !file_name.ends_with(ARCHIVE_EXTENSION) {
+        return Err(AppError::FileSystemError(format!(
+            "There is no archive {}",
+            file_name
+        )));
+    }
+    log::warn!("Deleting archive {:?}", archive_path);
+    std::fs::remove_file(archive_path)?;
+    Ok(())
+}
+
+/// Resolves the directory of a version or profile, refusing the one the app is running on
+fn resolve_removable_dir(
+    fs: &AppFileSystem,
+    version: &str,
+    profile: Option<&str>,
+) -> AppResult<PathBuf> {
+    check_name(version)?;
+    let mut target = root_dir(fs)?.join(version);
+    if version_of_dir(&target).is_none() {
+        return Err(AppError::FileSystemError(format!(
+            "{:?} is not the data directory of a version of the app",
+            target
+        )));
+    }
+    if let Some(profile) = profile {
+        check_name(profile)?;
+        target = target.join(profile);
+    }
+
+    if !target.is_dir() {
+        return Err(AppError::FileSystemError(format!(
+            "There is no data directory {:?}",
+            target
+        )));
+    }
+    if is_in_use(fs, &target) {
+        return Err(AppError::FileSystemError(format!(
+            "{:?} is in use by the running app and can't be removed",
+            target
+        )));
+    }
+    Ok(target)
+}
+
+/// Whether the directory is or contains the data directory of the running profile
+fn is_in_use(fs: &AppFileSystem, dir: &Path) -> bool {
+    fs.profile_data_dir.starts_with(dir)
+}
+
+/// Only plain directory names are accepted from the UI, never paths
+fn check_name(name: &str) -> AppResult<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') || name.contains('\\') {
+        return Err(AppError::FileSystemError(format!(
+            "Invalid directory name {:?}",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Directory holding the data directories of all versions of the app
+fn root_dir(fs: &AppFileSystem) -> AppResult<PathBuf> {
+    fs.app_data_dir
+        .parent()
+        .map(PathBuf::from)
+        .ok_or(AppError::FileSystemError(String::from(
+            "Could not resolve the data dir of all versions of this app",
+        )))
+}
+
+fn write_archive(dir: &Path, name: &str, archive_path: &Path) -> std::io::Result<()> {
+    let mut builder = tar::Builder::new(GzEncoder::new(
+        File::create(archive_path)?,
+        Compression::default(),
+    ));
+    append_dir(&mut builder, dir, Path::new(name))?;
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Appends the files of a directory recursively. Sockets and other special files, like the
+/// socket of a lair keystore, are skipped.
+fn append_dir<W: std::io::Write>(
+    builder: &mut tar::Builder<W>,
+    dir: &Path,
+    name: &Path,
+) -> std::io::Result<()> {
+    builder.append_dir(name, dir)?;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let entry_name = name.join(entry.file_name());
+        if file_type.is_dir() {
+            append_dir(builder, &entry.path(), &entry_name)?;
+        } else if file_type.is_file() {
+            builder.append_path_with_name(entry.path(), entry_name)?;
+        }
+    }
+    Ok(())
+}
+
+/// Size of the files in a directory, not following symlinks
+fn dir_size(dir: &Path) -> u64 {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(file_type) if file_type.is_file() => {
+                entry.metadata().map(|metadata| metadata.len()).unwrap_or(0)
+            }
+            _ => 0,
+        })
+        .sum()
+}
+
+fn sub_dirs(dir: &Path) -> AppResult<Vec<PathBuf>> {
+    Ok(std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .map(|entry| entry.path())
+        .collect())
+}
@@ -1,12 +1,14 @@
 use holochain_client::AdminWebsocket;
-use std::path::PathBuf;
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
 use tauri::AppHandle;
 
 use crate::errors::{AppError, AppResult, LairKeystoreError};
 
-#[allow(dead_code)]
 pub async fn get_admin_ws(admin_port: u16) -> AppResult<AdminWebsocket> {
-    let admin_ws = AdminWebsocket::connect(format!("ws://localhost:{}", admin_port))
+    let admin_ws = AdminWebsocket::connect(SocketAddr::from(([127, 0, 0, 1], admin_port)))
         .await
         .map_err(|err| {
             AppError::AdminWebsocketError(format!(
@@ -18,7 +20,6 @@ pub async fn get_admin_ws(admin_port: u16) -> AppResult<AdminWebsocket> {
     Ok(admin_ws)
 }
 
-#[allow(dead_code)]
 pub fn vec_to_locked(mut pass_tmp: Vec<u8>) -> std::io::Result<sodoken::BufRead> {
     match sodoken::BufWrite::new_mem_locked(pass_tmp.len()) {
         Err(e) => {
@@ -144,6 +145,34 @@ impl<'a> Iterator for LinesWithEndings<'a> {
     }
 }
 
+/// Copies a directory recursively. Sockets and other special files, like the socket of a
+/// running lair keystore, are skipped.
+pub fn copy_dir_all(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir_all(&entry.path(), &to.join(entry.file_name()))?;
+        } else if file_type.is_file() {
+            std::fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the last component of the path, e.g. the version or profile a data directory is for
+pub fn dir_name(dir: &Path) -> String {
+    dir.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Parses the breaking version a data directory is named after, e.g. `0.2.x` or `0.1.0-alpha.1`
+pub fn version_of_dir(dir: &Path) -> Option<semver::Version> {
+    semver::Version::parse(&dir_name(dir).replace(".x", ".0")).ok()
+}
+
 /// Returns a string considering the relevant part of the version regarding breaking changes
 /// Examples:
 /// 3.2.0 becomes 3.x.x